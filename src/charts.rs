@@ -0,0 +1,395 @@
+use crate::models::SenzorData;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Barvy jednotlivých sérií (senzorů) - opakují se dokola
+const PALETTE: [&str; 8] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b", "#e377c2", "#17becf",
+];
+
+/// Okraje grafu (místo pro popisky os a legendu)
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 30.0;
+const MARGIN_BOTTOM: f64 = 50.0;
+
+/// Počet popisků na ose X a Y
+const X_TICKS: usize = 6;
+const Y_TICKS: usize = 5;
+
+/// Rozměry legendy: značka barvy, odhad šířky znaku popisku,
+/// mezera mezi položkami a výška jednoho řádku
+const LEGEND_SWATCH: f64 = 16.0;
+const LEGEND_CHAR_WIDTH: f64 = 6.5;
+const LEGEND_GAP: f64 = 20.0;
+const LEGEND_ROW_HEIGHT: f64 = 16.0;
+
+/// Nastavení vykreslení grafu
+pub struct ChartOptions {
+    pub width: u32,
+    pub height: u32,
+    pub title: Option<String>,
+    // Na kolik sloupců se data seskupí. V každém sloupci se kreslí
+    // průměr jako čára a min/max jako poloprůhledný pás.
+    pub bins: usize,
+}
+
+impl Default for ChartOptions {
+    fn default() -> Self {
+        Self {
+            width: 900,
+            height: 320,
+            title: None,
+            bins: 200,
+        }
+    }
+}
+
+/// Jeden sloupec seskupených hodnot jedné série
+struct Bin {
+    time: DateTime<Utc>,
+    min: f64,
+    max: f64,
+    avg: f64,
+}
+
+/// Vykreslí čárový graf jako samostatný '<svg>' element.
+///
+/// Každý senzor je jedna série, data nemusí být seřazená.
+/// Výsledek lze vložit přímo do Tera šablony ('| safe')
+/// nebo poslat jako 'image/svg+xml'. Nepotřebuje žádný JavaScript.
+///
+/// Hodnoty NaN a nekonečno se přeskočí. Když se legenda nevejde
+/// na jeden řádek, zalomí se a obrázek se o další řádky prodlouží.
+pub fn render_line_chart(data: &[SenzorData], options: &ChartOptions) -> String {
+    let data: Vec<&SenzorData> = data.iter().filter(|d| d.hodnota.is_finite()).collect();
+
+    let mut series: BTreeMap<&str, Vec<&SenzorData>> = BTreeMap::new();
+    for point in &data {
+        series.entry(point.senzor_id.as_str()).or_default().push(point);
+    }
+    let names: Vec<&str> = series.keys().copied().collect();
+    let legend = legend_layout(&names, options.width as f64);
+    let legend_rows = legend.last().map_or(1, |(_, row)| row + 1);
+
+    let width = options.width as f64;
+    let plot_w = (width - MARGIN_LEFT - MARGIN_RIGHT).max(1.0);
+    let plot_h = (options.height as f64 - MARGIN_TOP - MARGIN_BOTTOM).max(1.0);
+    let height = options.height as f64 + (legend_rows - 1) as f64 * LEGEND_ROW_HEIGHT;
+
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {w} {h}" width="{w}" height="{h}" font-family="sans-serif" font-size="11">"#,
+        w = options.width,
+        h = height
+    );
+    let _ = write!(svg, r##"<rect width="{}" height="{}" fill="#ffffff"/>"##, width, height);
+
+    if let Some(title) = &options.title {
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="18" font-size="14" font-weight="bold">{}</text>"#,
+            MARGIN_LEFT,
+            escape(title)
+        );
+    }
+
+    // 1. Rozsahy os
+    let (Some(t_min), Some(t_max)) = (
+        data.iter().map(|d| d.time).min(),
+        data.iter().map(|d| d.time).max(),
+    ) else {
+        let _ = write!(
+            svg,
+            r##"<text x="{}" y="{}" text-anchor="middle" fill="#666">Žádná data</text></svg>"##,
+            width / 2.0,
+            height / 2.0
+        );
+        return svg;
+    };
+    let (v_min, v_max) = nice_range(
+        data.iter().map(|d| d.hodnota).fold(f64::INFINITY, f64::min),
+        data.iter().map(|d| d.hodnota).fold(f64::NEG_INFINITY, f64::max),
+    );
+
+    let span_ms = (t_max - t_min).num_milliseconds().max(1) as f64;
+    let x_of = |t: DateTime<Utc>| {
+        MARGIN_LEFT + (t - t_min).num_milliseconds() as f64 / span_ms * plot_w
+    };
+    let y_of = |v: f64| MARGIN_TOP + plot_h - (v - v_min) / (v_max - v_min) * plot_h;
+
+    // 2. Mřížka a osa Y
+    for i in 0..=Y_TICKS {
+        let value = v_min + (v_max - v_min) * i as f64 / Y_TICKS as f64;
+        let y = y_of(value);
+        let _ = write!(
+            svg,
+            r##"<line x1="{x1}" y1="{y:.1}" x2="{x2}" y2="{y:.1}" stroke="#e0e0e0"/><text x="{tx}" y="{ty:.1}" text-anchor="end" fill="#333">{label}</text>"##,
+            x1 = MARGIN_LEFT,
+            x2 = MARGIN_LEFT + plot_w,
+            tx = MARGIN_LEFT - 6.0,
+            ty = y + 4.0,
+            label = format_value(value),
+        );
+    }
+
+    // 3. Osa X s časovými popisky
+    let time_format = time_label_format(t_max - t_min);
+    for i in 0..X_TICKS {
+        let offset_ms = (span_ms * i as f64 / (X_TICKS - 1) as f64) as i64;
+        let t = t_min + chrono::Duration::milliseconds(offset_ms);
+        let x = x_of(t);
+        let _ = write!(
+            svg,
+            r##"<line x1="{x:.1}" y1="{y1}" x2="{x:.1}" y2="{y2}" stroke="#333"/><text x="{x:.1}" y="{ty}" text-anchor="middle" fill="#333">{label}</text>"##,
+            y1 = MARGIN_TOP + plot_h,
+            y2 = MARGIN_TOP + plot_h + 5.0,
+            ty = MARGIN_TOP + plot_h + 18.0,
+            label = t.format(time_format),
+        );
+    }
+    let _ = write!(
+        svg,
+        r##"<line x1="{l}" y1="{t}" x2="{l}" y2="{b}" stroke="#333"/><line x1="{l}" y1="{b}" x2="{r}" y2="{b}" stroke="#333"/>"##,
+        l = MARGIN_LEFT,
+        r = MARGIN_LEFT + plot_w,
+        t = MARGIN_TOP,
+        b = MARGIN_TOP + plot_h,
+    );
+
+    // 4. Série - jedna pro každý senzor
+    for (index, (senzor_id, mut points)) in series.into_iter().enumerate() {
+        let color = PALETTE[index % PALETTE.len()];
+        points.sort_by_key(|p| p.time);
+        let bins = bin_points(&points, t_min, span_ms, options.bins.max(1));
+
+        // Min/max pás (jen pokud se v některém sloupci sešlo víc hodnot)
+        if bins.iter().any(|b| b.max > b.min) {
+            let mut band = String::new();
+            for bin in &bins {
+                let _ = write!(band, "{:.1},{:.1} ", x_of(bin.time), y_of(bin.max));
+            }
+            for bin in bins.iter().rev() {
+                let _ = write!(band, "{:.1},{:.1} ", x_of(bin.time), y_of(bin.min));
+            }
+            let _ = write!(
+                svg,
+                r#"<polygon points="{}" fill="{}" fill-opacity="0.15" stroke="none"/>"#,
+                band.trim_end(),
+                color
+            );
+        }
+
+        // Čára průměrů
+        let mut line = String::new();
+        for bin in &bins {
+            let _ = write!(line, "{:.1},{:.1} ", x_of(bin.time), y_of(bin.avg));
+        }
+        let _ = write!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"><title>{}</title></polyline>"#,
+            line.trim_end(),
+            color,
+            escape(senzor_id)
+        );
+
+        // Legenda pod osou X
+        let (offset, row) = legend[index];
+        let legend_x = MARGIN_LEFT + offset;
+        let legend_y = options.height as f64 - 10.0 + row as f64 * LEGEND_ROW_HEIGHT;
+        let _ = write!(
+            svg,
+            r#"<rect x="{lx}" y="{ry}" width="12" height="3" fill="{c}"/><text x="{tx}" y="{ty}">{name}</text>"#,
+            lx = legend_x,
+            ry = legend_y - 4.0,
+            c = color,
+            tx = legend_x + 16.0,
+            ty = legend_y,
+            name = escape(senzor_id),
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Rozmístí položky legendy do řádků podle délky popisků.
+///
+/// Vrací pro každou položku posun od levého okraje grafu a číslo řádku.
+fn legend_layout(names: &[&str], width: f64) -> Vec<(f64, usize)> {
+    let available = (width - MARGIN_LEFT - MARGIN_RIGHT).max(1.0);
+    let mut layout = Vec::with_capacity(names.len());
+    let (mut x, mut row) = (0.0, 0);
+    for name in names {
+        let entry = LEGEND_SWATCH + name.chars().count() as f64 * LEGEND_CHAR_WIDTH;
+        if x > 0.0 && x + entry > available {
+            x = 0.0;
+            row += 1;
+        }
+        layout.push((x, row));
+        x += entry + LEGEND_GAP;
+    }
+    layout
+}
+
+/// Seskupí seřazené body do sloupců podle času.
+///
+/// Čas sloupce je čas prvního bodu v něm, aby čára
+/// nezačínala ani nekončila mimo skutečná data.
+fn bin_points(
+    points: &[&SenzorData],
+    t_min: DateTime<Utc>,
+    span_ms: f64,
+    bins: usize,
+) -> Vec<Bin> {
+    let mut result: Vec<(usize, Bin, usize)> = Vec::new();
+    for point in points {
+        let position = (point.time - t_min).num_milliseconds() as f64 / span_ms;
+        let index = ((position * bins as f64) as usize).min(bins - 1);
+        match result.last_mut() {
+            Some((last_index, bin, count)) if *last_index == index => {
+                bin.min = bin.min.min(point.hodnota);
+                bin.max = bin.max.max(point.hodnota);
+                bin.avg += point.hodnota;
+                *count += 1;
+            }
+            _ => result.push((
+                index,
+                Bin {
+                    time: point.time,
+                    min: point.hodnota,
+                    max: point.hodnota,
+                    avg: point.hodnota,
+                },
+                1,
+            )),
+        }
+    }
+    result
+        .into_iter()
+        .map(|(_, mut bin, count)| {
+            bin.avg /= count as f64;
+            bin
+        })
+        .collect()
+}
+
+/// Rozšíří rozsah hodnot na "hezká" čísla (a ošetří konstantní data)
+fn nice_range(min: f64, max: f64) -> (f64, f64) {
+    if (max - min).abs() < f64::EPSILON {
+        return (min - 1.0, max + 1.0);
+    }
+    let step = 10f64.powf(((max - min) / Y_TICKS as f64).log10().floor());
+    ((min / step).floor() * step, (max / step).ceil() * step)
+}
+
+fn format_value(value: f64) -> String {
+    if value.abs() >= 100.0 || value.fract().abs() < 1e-9 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Formát časových popisků podle délky zobrazeného rozsahu
+fn time_label_format(span: chrono::Duration) -> &'static str {
+    if span <= chrono::Duration::days(1) {
+        "%H:%M"
+    } else if span <= chrono::Duration::days(7) {
+        "%d.%m. %H:%M"
+    } else {
+        "%d.%m.%Y"
+    }
+}
+
+/// Escapuje text pro vložení do SVG/XML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(senzor_id: &str, secs: i64, hodnota: f64) -> SenzorData {
+        SenzorData {
+            time: DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap(),
+            senzor_id: senzor_id.to_string(),
+            hodnota,
+        }
+    }
+
+    #[test]
+    fn nice_range_rounds_to_step() {
+        assert_eq!(nice_range(0.3, 9.7), (0.0, 10.0));
+        assert_eq!(nice_range(-12.0, 47.0), (-20.0, 50.0));
+        assert_eq!(nice_range(5.0, 5.0), (4.0, 6.0));
+    }
+
+    #[test]
+    fn bin_points_groups_by_time() {
+        let points = [point("a", 0, 1.0), point("a", 1, 3.0), point("a", 60, 10.0)];
+        let refs: Vec<&SenzorData> = points.iter().collect();
+        let bins = bin_points(&refs, points[0].time, 60_000.0, 2);
+
+        assert_eq!(bins.len(), 2);
+        assert_eq!(bins[0].time, points[0].time);
+        assert_eq!((bins[0].min, bins[0].max, bins[0].avg), (1.0, 3.0, 2.0));
+        // Poslední bod padne do posledního sloupce, ne za něj
+        assert_eq!(bins[1].time, points[2].time);
+        assert_eq!((bins[1].min, bins[1].max, bins[1].avg), (10.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn empty_or_non_finite_data_shows_placeholder() {
+        let options = ChartOptions::default();
+        assert!(render_line_chart(&[], &options).contains("Žádná data"));
+
+        let invalid = [point("a", 0, f64::NAN), point("a", 1, f64::INFINITY)];
+        assert!(render_line_chart(&invalid, &options).contains("Žádná data"));
+    }
+
+    #[test]
+    fn non_finite_values_are_skipped() {
+        let data = [
+            point("a", 0, 1.0),
+            point("a", 10, f64::NAN),
+            point("a", 20, f64::NEG_INFINITY),
+            point("a", 30, 2.0),
+        ];
+        let svg = render_line_chart(&data, &ChartOptions::default());
+        assert!(svg.contains("<polyline"));
+        assert!(!svg.contains("NaN"));
+        assert!(!svg.contains("inf"));
+    }
+
+    #[test]
+    fn legend_wraps_into_rows() {
+        let names: Vec<String> = (0..50).map(|i| format!("senzor-{:02}", i)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let layout = legend_layout(&names, 900.0);
+
+        let available = 900.0 - MARGIN_LEFT - MARGIN_RIGHT;
+        for ((x, _), name) in layout.iter().zip(&names) {
+            assert!(x + LEGEND_SWATCH + name.len() as f64 * LEGEND_CHAR_WIDTH <= available);
+        }
+        assert!(layout.last().unwrap().1 > 0);
+
+        let data: Vec<SenzorData> = names.iter().map(|name| point(name, 0, 1.0)).collect();
+        let svg = render_line_chart(&data, &ChartOptions::default());
+        let rows = layout.last().unwrap().1 as f64;
+        assert!(svg.contains(&format!(r#"height="{}""#, 320.0 + rows * LEGEND_ROW_HEIGHT)));
+    }
+
+    #[test]
+    fn single_legend_row_keeps_height() {
+        let data = [point("a", 0, 1.0), point("b", 60, 2.0)];
+        let svg = render_line_chart(&data, &ChartOptions::default());
+        assert!(svg.contains(r#"viewBox="0 0 900 320""#));
+    }
+}
@@ -1,7 +1,6 @@
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_appender::rolling;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
-/// , Layer};
 
 /// Nastaví globální 'tracing' subscriber pro celou aplikaci.
 ///
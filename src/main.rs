@@ -1,14 +1,17 @@
 // Zjednodušený import
-use axum::{routing::get, Router};
+use axum::{routing::{get, post}, Router};
 
 //use crate::state::{AppState, AppStateBuilder};
+use crate::config::{Cli, Config};
 use crate::state::AppStateBuilder;
-use std::env;
+use clap::Parser;
 use std::net::SocketAddr;
 use tower_http::services::ServeDir;
 use tracing::info;
 
 // Ostatní moduly (main.rs nemusí znát `Json`)
+mod charts;
+mod config;
 mod logging;
 mod models;
 mod routes;
@@ -20,16 +23,28 @@ async fn main() {
     dotenvy::dotenv().ok();
     let _logging_guard = logging::setup_logging();
 
-    let db_url = env::var("DATABASE_URL").expect("Proměnná DATABASE_URL není nastavena");
-    let valkey_url = env::var("VALKEY_URL").expect("Proměnná VALKEY_URL není nastavena");
-    let manticore_url = env::var("MANTICORE_URL").expect("Proměnná MANTICORE_URL není nastavena");
+    // Konfigurace: výchozí hodnoty < TOML soubor < env < CLI.
+    // Chybějící URL není fatální - dashboard naběhne a daný
+    // backend zobrazí jako nedostupný. Neplatné hodnoty ale ano.
+    let cli = Cli::parse();
+    let config = Config::load(&cli).unwrap_or_else(|e| {
+        tracing::error!("{}", e);
+        std::process::exit(2);
+    });
+
+    if cli.print_config {
+        println!("{}", config.to_redacted_toml());
+        return;
+    }
+
+    // Validace už ověřila, že adresa je platná
+    let addr: SocketAddr = config.app.bind_addr.parse().unwrap_or_else(|_| {
+        SocketAddr::from(([0, 0, 0, 0], 8080))
+    });
 
-    info!("Sestavuji AppState a připojuji k DB...");
+    info!("Sestavuji AppState, backendy se připojí na pozadí...");
     let app_state = AppStateBuilder::new()
-        .app_name("RPi Dashboard")
-        .postgres_url(db_url)
-        .valkey_url(valkey_url)
-        .manticore_url(manticore_url)
+        .config(config)
         .build()
         .await;
 
@@ -49,13 +64,30 @@ async fn main() {
         .route("/", get(routes::web::page_index))
         .route("/postgres", get(routes::web::page_postgres))
         .route("/valkey", get(routes::web::page_valkey))
+        .route("/valkey/key", get(routes::web::page_valkey_key))
+        .route("/valkey/server", get(routes::web::page_valkey_server))
         .route("/timescale", get(routes::web::page_timescale))
+        .route("/charts/sensor/:file", get(routes::web::chart_sensor_svg))
         .route("/mqtt", get(routes::web::page_mqtt))
+        .route("/mqtt/topics", get(routes::web::page_mqtt_topics))
+        .route("/mqtt/live", get(routes::web::page_mqtt_live))
         .route("/search", get(routes::web::page_search))
+        .route("/search/admin", get(routes::web::page_search_admin).post(routes::web::post_search_admin))
+        .route("/health", get(routes::web::page_health))
+        // Sondy pro Kubernetes (liveness / readiness)
+        .route("/healthz", get(routes::api::get_healthz))
+        .route("/readyz", get(routes::api::get_readyz))
         // Vnoříme všechny API routy pod prefix /api/v1
         .nest("/api/v1", 
             Router::new()
                 .route("/mqtt/stats", get(routes::api::get_mqtt_stats))
+                .route("/mqtt/stats/history", get(routes::api::get_mqtt_stats_history))
+                .route("/mqtt/stats/stream", get(routes::api::get_mqtt_stats_stream))
+                .route("/mqtt/publish", post(routes::api::post_mqtt_publish))
+                .route("/mqtt/live", get(routes::api::get_mqtt_live))
+                .route("/sensors/:id/aggregate", get(routes::api::get_sensor_aggregate))
+                .route("/valkey/key", get(routes::api::get_valkey_key))
+                .route("/valkey/info", get(routes::api::get_valkey_info))
             // Zde by přišly další API routy...
         )
         // AŽ TEĎ, úplně na konci, aplikujeme stav na VŠECHNY routy
@@ -66,11 +98,10 @@ async fn main() {
         .merge(app_with_state)
         .merge(static_router);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
     info!("Server bude naslouchat na adrese http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap_or_else(|e| {
-        tracing::error!("Nepodařilo se bindovat na port 8080: {}", e);
+        tracing::error!("Nepodařilo se bindovat na adresu {}: {}", addr, e);
         std::process::exit(1);
     });
 
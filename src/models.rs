@@ -1,6 +1,7 @@
 use serde::{Serialize, Deserialize};
 use sqlx::FromRow;
 use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 
 /// Toto je náš "View Model" pro hlavní stránku.
 ///
@@ -26,12 +27,240 @@ pub struct PostgresTemplate {
     pub polozky: Vec<MojeData>,
 }
 
-/// View Model pro 'valkey.html' šablonu
+/// Informace o jednom klíči ve Valkey (z TYPE, TTL a MEMORY USAGE)
+#[derive(Debug, Clone, Serialize)]
+pub struct ValkeyKeyInfo {
+    pub key: String,
+    // Hex podoba klíče, který není platné UTF-8 ('key' je pak jen
+    // přibližný a detail se otevírá přes '?key_raw=')
+    pub key_raw: Option<String>,
+    // 'string', 'hash', 'list', 'set', 'zset', 'stream' ('none' = neexistuje)
+    pub key_type: String,
+    // TTL v sekundách, -1 = bez expirace, -2 = klíč neexistuje
+    pub ttl: i64,
+    pub memory_bytes: Option<u64>,
+}
+
+/// View Model pro 'valkey.html' šablonu (prohlížeč klíčů)
 #[derive(Serialize)]
 pub struct ValkeyTemplate {
+    pub pattern: String,
+    pub count: usize,
+    // Kurzor aktuální stránky a kurzor další stránky (0 = konec)
+    pub cursor: u64,
+    pub next_cursor: u64,
+    pub keys: Vec<ValkeyKeyInfo>,
+    // Klíč z konfigurace, na který odkazujeme jako na "stavový"
+    pub status_key: String,
+    pub errors: Vec<String>,
+}
+
+/// Stránkování při čtení hodnoty klíče. Každý typ používá jen
+/// svou část: hash/set 'cursor' (HSCAN/SSCAN), list/zset 'offset'
+/// (LRANGE/ZRANGE), stream 'from_id' (XRANGE).
+#[derive(Debug, Clone)]
+pub struct ValkeyPage {
+    pub cursor: u64,
+    pub offset: i64,
+    pub from_id: Option<String>,
+    pub count: usize,
+}
+
+/// Pole hashe
+#[derive(Debug, Clone, Serialize)]
+pub struct ValkeyHashField {
+    pub field: String,
+    pub value: String,
+}
+
+/// Prvek listu i s jeho indexem
+#[derive(Debug, Clone, Serialize)]
+pub struct ValkeyListItem {
+    pub index: i64,
+    pub value: String,
+}
+
+/// Člen sorted setu se skóre
+#[derive(Debug, Clone, Serialize)]
+pub struct ValkeyZsetMember {
+    pub member: String,
+    pub score: f64,
+}
+
+/// Jeden záznam streamu
+#[derive(Debug, Clone, Serialize)]
+pub struct ValkeyStreamEntry {
+    pub id: String,
+    pub fields: Vec<ValkeyHashField>,
+}
+
+/// Hodnota klíče podle jeho typu.
+///
+/// Serializuje se s polem 'type', takže šablona i JSON API
+/// mohou jednoduše rozlišit, jakou tabulku zobrazit.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ValkeyValue {
+    String {
+        value: Option<String>,
+    },
+    Hash {
+        total: i64,
+        fields: Vec<ValkeyHashField>,
+        // 0 = žádná další stránka
+        next_cursor: u64,
+    },
+    List {
+        total: i64,
+        items: Vec<ValkeyListItem>,
+        next_offset: Option<i64>,
+    },
+    Set {
+        total: i64,
+        members: Vec<String>,
+        next_cursor: u64,
+    },
+    Zset {
+        total: i64,
+        members: Vec<ValkeyZsetMember>,
+        next_offset: Option<i64>,
+    },
+    Stream {
+        total: i64,
+        entries: Vec<ValkeyStreamEntry>,
+        // Exkluzivní začátek další stránky ('(' + poslední ID)
+        next_from_id: Option<String>,
+    },
+    Unsupported {
+        key_type: String,
+    },
+}
+
+/// View Model pro 'valkey_key.html' šablonu (detail klíče).
+/// Stejná struktura se vrací i z JSON API.
+#[derive(Serialize)]
+pub struct ValkeyKeyTemplate {
     pub key: String,
-    pub value: String, // Místo 'info_string'
+    // Viz 'ValkeyKeyInfo::key_raw'
+    pub key_raw: Option<String>,
+    // 'None' = klíč neexistuje
+    pub info: Option<ValkeyKeyInfo>,
+    pub value: Option<ValkeyValue>,
+    pub count: usize,
 }
+/// Sekce 'Memory' z Valkey 'INFO'
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValkeyMemoryInfo {
+    pub used_memory: u64,
+    pub used_memory_human: String,
+    pub used_memory_rss: u64,
+    pub used_memory_peak: u64,
+    // 0 = bez limitu
+    pub maxmemory: u64,
+    pub maxmemory_policy: String,
+    pub mem_fragmentation_ratio: f64,
+    // Využití limitu v procentech (jen pokud je 'maxmemory' nastaven)
+    pub usage_percent: Option<f64>,
+}
+
+/// Sekce 'Clients' z Valkey 'INFO'
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValkeyClientsInfo {
+    pub connected_clients: u64,
+    pub blocked_clients: u64,
+    pub maxclients: u64,
+}
+
+/// Sekce 'Persistence' z Valkey 'INFO' (RDB snapshoty a AOF)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValkeyPersistenceInfo {
+    pub loading: bool,
+    pub rdb_changes_since_last_save: u64,
+    pub rdb_bgsave_in_progress: bool,
+    pub rdb_last_save_time: Option<DateTime<Utc>>,
+    pub rdb_last_bgsave_status: String,
+    pub aof_enabled: bool,
+    pub aof_rewrite_in_progress: bool,
+    pub aof_last_bgrewrite_status: String,
+    pub aof_last_write_status: String,
+}
+
+/// Sekce 'Replication' z Valkey 'INFO'
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValkeyReplicationInfo {
+    pub role: String,
+    pub connected_slaves: u64,
+    pub master_host: Option<String>,
+    pub master_link_status: Option<String>,
+}
+
+/// Sekce 'Stats' z Valkey 'INFO' včetně úspěšnosti cache
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValkeyStatsInfo {
+    pub total_commands_processed: u64,
+    pub instantaneous_ops_per_sec: u64,
+    pub keyspace_hits: u64,
+    pub keyspace_misses: u64,
+    // hits / (hits + misses) v procentech, 'None' pokud ještě nebyl dotaz
+    pub hit_ratio_percent: Option<f64>,
+    pub expired_keys: u64,
+    pub evicted_keys: u64,
+}
+
+/// Jeden řádek sekce 'Keyspace' (např. 'db0:keys=10,expires=2,avg_ttl=0')
+#[derive(Debug, Clone, Serialize)]
+pub struct ValkeyKeyspaceDb {
+    pub db: String,
+    pub keys: u64,
+    pub expires: u64,
+    pub avg_ttl: u64,
+}
+
+/// Typovaný výstup příkazu 'INFO'
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValkeyServerInfo {
+    pub version: String,
+    pub uptime_seconds: u64,
+    pub memory: ValkeyMemoryInfo,
+    pub clients: ValkeyClientsInfo,
+    pub persistence: ValkeyPersistenceInfo,
+    pub replication: ValkeyReplicationInfo,
+    pub stats: ValkeyStatsInfo,
+    pub keyspace: Vec<ValkeyKeyspaceDb>,
+}
+
+/// Jeden záznam z 'SLOWLOG GET'
+#[derive(Debug, Clone, Serialize)]
+pub struct ValkeySlowlogEntry {
+    pub id: u64,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub duration_us: u64,
+    pub command: String,
+    pub client_addr: String,
+    pub client_name: String,
+}
+
+/// Jeden řádek z 'CLIENT LIST'
+#[derive(Debug, Clone, Serialize)]
+pub struct ValkeyClientEntry {
+    pub id: String,
+    pub addr: String,
+    pub name: String,
+    pub age_seconds: u64,
+    pub idle_seconds: u64,
+    pub db: String,
+    pub flags: String,
+    pub cmd: String,
+}
+
+/// View Model pro 'valkey_server.html' (a odpověď '/api/v1/valkey/info')
+#[derive(Serialize)]
+pub struct ValkeyServerTemplate {
+    pub info: ValkeyServerInfo,
+    pub slowlog: Vec<ValkeySlowlogEntry>,
+    pub clients: Vec<ValkeyClientEntry>,
+}
+
 /// Datová struktura pro řádek z TimescaleDB
 #[derive(Debug, FromRow, Serialize)]
 pub struct SenzorData {
@@ -42,35 +271,432 @@ pub struct SenzorData {
     pub hodnota: f64,
 }
 
+/// Velikost "kbelíku" pro agregaci přes TimescaleDB 'time_bucket'
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BucketInterval {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl BucketInterval {
+    /// Hodnota pro Postgres typ 'interval' (předává se jako bind parametr)
+    pub fn as_pg_interval(&self) -> &'static str {
+        match self {
+            BucketInterval::OneMinute => "1 minute",
+            BucketInterval::FiveMinutes => "5 minutes",
+            BucketInterval::OneHour => "1 hour",
+            BucketInterval::OneDay => "1 day",
+        }
+    }
+
+    pub fn duration(&self) -> chrono::Duration {
+        match self {
+            BucketInterval::OneMinute => chrono::Duration::minutes(1),
+            BucketInterval::FiveMinutes => chrono::Duration::minutes(5),
+            BucketInterval::OneHour => chrono::Duration::hours(1),
+            BucketInterval::OneDay => chrono::Duration::days(1),
+        }
+    }
+}
+
+impl std::str::FromStr for BucketInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1m" => Ok(BucketInterval::OneMinute),
+            "5m" => Ok(BucketInterval::FiveMinutes),
+            "1h" => Ok(BucketInterval::OneHour),
+            "1d" => Ok(BucketInterval::OneDay),
+            other => Err(format!(
+                "Neznámý interval '{}' (povoleno: 1m, 5m, 1h, 1d)",
+                other
+            )),
+        }
+    }
+}
+
+/// Jeden agregovaný "kbelík" dat ze senzoru
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct SenzorAggregate {
+    pub bucket: DateTime<Utc>,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub count: i64,
+    // Poslední hodnota v kbelíku (TimescaleDB 'last(hodnota, time)')
+    pub last: f64,
+}
+
+/// Odpověď API '/api/v1/sensors/:id/aggregate'
+#[derive(Debug, Serialize)]
+pub struct SenzorAggregateResponse {
+    pub senzor_id: String,
+    pub interval: BucketInterval,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub buckets: Vec<SenzorAggregate>,
+}
+
+/// Zvalidovaný filtr pro dotaz do 'senzor_data'
+#[derive(Debug, Clone)]
+pub struct TimescaleFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    // Prázdný vektor = všechny senzory
+    pub senzor_ids: Vec<String>,
+    pub limit: i64,
+}
+
 /// View Model pro 'timescale.html' šablonu
 #[derive(Serialize)]
 pub struct TimescaleTemplate {
+    // Graf vykreslený modulem 'charts' (vkládá se přes '| safe')
+    pub chart_svg: String,
     pub senzory: Vec<SenzorData>,
+    // Hodnoty formuláře tak, jak je uživatel zadal
+    pub from: String,
+    pub to: String,
+    pub selected_ids: Vec<String>,
+    pub limit: i64,
+    // Všechny známé senzory pro výběr ve formuláři
+    pub available_ids: Vec<String>,
+    // Chyby validace parametrů (dotaz se pak nespustí)
+    pub errors: Vec<String>,
 }
 
-/// Struktura pro uložení nasbíraných $SYS statistik
+/// Struktura pro uložení nasbíraných $SYS statistik (Mosquitto).
 /// 'Default' nám umožní vytvořit prázdnou instanci.
+///
+/// Všechny hodnoty jsou 'Option' - broker je posílá postupně
+/// a starší verze Mosquitta některá témata vůbec nemají.
 #[derive(Debug, Default, Serialize, Clone)]
 pub struct MqttStats {
-    pub uptime: String,
-    pub clients_connected: String,
-    pub messages_sent: String,
-    pub messages_received: String,
+    pub version: Option<String>,
+    pub uptime_seconds: Option<u64>,
+    pub clients: MqttClientStats,
+    pub messages: MqttCounters,
+    pub bytes: MqttCounters,
+    pub publish: MqttPublishStats,
+    pub subscriptions: Option<u64>,
+    pub retained: Option<u64>,
+    pub stored: Option<u64>,
+    pub heap: MqttHeapStats,
+    pub load: MqttLoadStats,
+    // Odvozené rychlosti za sekundu (z po sobě jdoucích vzorků)
+    pub rates: MqttRates,
+    // Kdy přišla poslední $SYS hodnota
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// '$SYS/broker/clients/...'
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct MqttClientStats {
+    pub total: Option<u64>,
+    pub connected: Option<u64>,
+    pub disconnected: Option<u64>,
+    pub expired: Option<u64>,
+    pub maximum: Option<u64>,
+}
+
+/// Dvojice rostoucích čítačů (přijato / odesláno)
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct MqttCounters {
+    pub received: Option<u64>,
+    pub sent: Option<u64>,
+}
+
+/// '$SYS/broker/publish/messages/...'
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct MqttPublishStats {
+    pub received: Option<u64>,
+    pub sent: Option<u64>,
+    pub dropped: Option<u64>,
+}
+
+/// '$SYS/broker/heap/...' (v bajtech)
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct MqttHeapStats {
+    pub current: Option<u64>,
+    pub maximum: Option<u64>,
+}
+
+/// Klouzavé průměry za 1, 5 a 15 minut, jak je počítá broker
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct MqttLoadAverage {
+    pub min1: Option<f64>,
+    pub min5: Option<f64>,
+    pub min15: Option<f64>,
+}
+
+/// '$SYS/broker/load/...' - hodnoty za minutu
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct MqttLoadStats {
+    pub messages_received: MqttLoadAverage,
+    pub messages_sent: MqttLoadAverage,
+    pub bytes_received: MqttLoadAverage,
+    pub bytes_sent: MqttLoadAverage,
+    pub publish_received: MqttLoadAverage,
+    pub publish_sent: MqttLoadAverage,
+    pub publish_dropped: MqttLoadAverage,
+    pub connections: MqttLoadAverage,
+    pub sockets: MqttLoadAverage,
+}
+
+/// Průtok za sekundu spočítaný dashboardem z rozdílu dvou vzorků čítače
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct MqttRates {
+    pub messages_received: Option<f64>,
+    pub messages_sent: Option<f64>,
+    pub bytes_received: Option<f64>,
+    pub bytes_sent: Option<f64>,
+    pub publish_dropped: Option<f64>,
+}
+
+/// Jeden vzorek historie statistik brokeru (pro sparklines)
+#[derive(Debug, Serialize, Clone)]
+pub struct MqttStatsSample {
+    pub time: DateTime<Utc>,
+    pub clients_connected: Option<u64>,
+    pub subscriptions: Option<u64>,
+    pub retained: Option<u64>,
+    pub heap_current: Option<u64>,
+    pub messages_received_rate: Option<f64>,
+    pub messages_sent_rate: Option<f64>,
+    pub bytes_received_rate: Option<f64>,
+    pub bytes_sent_rate: Option<f64>,
+}
+
+impl MqttStatsSample {
+    /// Vytáhne z aktuálních statistik hodnoty, které sledujeme v čase
+    pub fn from_stats(stats: &MqttStats, time: DateTime<Utc>) -> Self {
+        Self {
+            time,
+            clients_connected: stats.clients.connected,
+            subscriptions: stats.subscriptions,
+            retained: stats.retained,
+            heap_current: stats.heap.current,
+            messages_received_rate: stats.rates.messages_received,
+            messages_sent_rate: stats.rates.messages_sent,
+            bytes_received_rate: stats.rates.bytes_received,
+            bytes_sent_rate: stats.rates.bytes_sent,
+        }
+    }
+}
+
+/// Odpověď '/api/v1/mqtt/stats/history'
+#[derive(Debug, Serialize)]
+pub struct MqttStatsHistoryResponse {
+    pub interval_secs: u64,
+    pub samples: Vec<MqttStatsSample>,
+}
+
+/// Stav spojení sběrače s MQTT brokerem (zobrazený na stránce /mqtt)
+#[derive(Debug, Clone, Serialize)]
+pub struct MqttConnection {
+    // Připojeno / znovu se připojuje, poslední chyba, počet pokusů
+    pub status: BackendStatus,
+    // Čas poslední přijaté zprávy (PINGRESP se nepočítá)
+    pub last_message_at: Option<DateTime<Utc>>,
+}
+
+/// Statistiky mostu MQTT -> Timescale (zobrazené na stránce /mqtt)
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct IngestStats {
+    // Zprávy namapované na řádek a předané do fronty
+    pub received: u64,
+    // Zprávy, jejichž payload nešel převést na číslo (nebo chybí JSONPath)
+    pub skipped: u64,
+    // Řádky úspěšně zapsané do 'senzor_data'
+    pub inserted: u64,
+    // Řádky, které databáze odmítla (i při zápisu po jednom)
+    pub dropped: u64,
+    pub batches: u64,
+    pub last_flush_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// Řádek pro indexer: ID dokumentu, vodoznak a dokument jako JSON
+/// (časové sloupce už převedené na unix timestamp)
+#[derive(Debug, FromRow)]
+pub struct IndexerRow {
+    pub doc_id: i64,
+    // Hodnota 'watermark_column' ('None', když tabulka žádný nemá)
+    pub watermark: Option<DateTime<Utc>>,
+    pub doc: String,
+}
+
+/// Stav indexeru Postgres -> Manticore (panel na stránce /search)
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct IndexerStats {
+    pub tables: Vec<IndexerTableStatus>,
+    pub last_pass_at: Option<DateTime<Utc>>,
+}
+
+/// Postup jedné tabulky. Vodoznak a počet se ukládají i do Valkey,
+/// po restartu indexer pokračuje od posledního zapsaného řádku.
+#[derive(Debug, Serialize, Clone)]
+pub struct IndexerTableStatus {
+    pub table: String,
+    pub index: String,
+    // Klíč ve Valkey s postupem
+    pub progress_key: String,
+    pub last_id: Option<i64>,
+    pub watermark: Option<DateTime<Utc>>,
+    // Celkem zapsaných dokumentů (včetně předchozích běhů)
+    pub indexed: u64,
+    // Poslední průchod došel na konec tabulky
+    pub caught_up: bool,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
 }
 
 /// View Model pro 'mqtt.html' šablonu
 #[derive(Serialize)]
 pub struct MqttTemplate {
+    // 'mqtt://host:port' nebo 'mqtts://host:port' (TLS)
     pub broker_host: String,
+    pub protocol: String,
+    // Uživatel, pod kterým se přihlašujeme ('None' = anonymně)
+    pub username: Option<String>,
     // Smažeme: pub stats: MqttStats,
     pub refresh_interval_ms: u64, // Přidáme interval
+    // Vyplněno, pokud sběrač zrovna nemá spojení s brokerem
+    pub unavailable: Option<UnavailableTemplate>,
+    pub connection: MqttConnection,
+    // Stav ingestu do Timescale ('None' = ingest je vypnutý)
+    pub ingest: Option<IngestStats>,
+}
+
+/// Tělo požadavku 'POST /api/v1/mqtt/publish'
+#[derive(Debug, Deserialize)]
+pub struct MqttPublishRequest {
+    pub topic: String,
+    // Prázdný payload s 'retain: true' smaže retained zprávu
+    #[serde(default)]
+    pub payload: String,
+    #[serde(default)]
+    pub qos: u8,
+    #[serde(default)]
+    pub retain: bool,
+}
+
+/// Poslední zpráva na MQTT tématu (pro stránku /mqtt/topics)
+#[derive(Debug, Serialize)]
+pub struct MqttTopicMessage {
+    // Dekódovaný payload jako text, zkrácený pro zobrazení
+    pub payload: String,
+    // Název dekodéru ('json', 'hex'...) a proč selhal ten nastavený
+    pub decoder: String,
+    pub decode_error: Option<String>,
+    pub payload_size: usize,
+    pub received_at: DateTime<Utc>,
+    pub qos: u8,
+    pub retain: bool,
+    pub count: u64,
+}
+
+/// Jeden řádek (uzel) ve stromu MQTT témat
+#[derive(Debug, Serialize)]
+pub struct MqttTopicRow {
+    // Celé téma, např. 'sensors/kuchyn/temp'
+    pub topic: String,
+    // Jen poslední úroveň, např. 'temp'
+    pub name: String,
+    pub depth: usize,
+    pub children: usize,
+    pub subtree_messages: u64,
+    // 'None' pro uzly, na které samotné zatím nic nepřišlo
+    pub message: Option<MqttTopicMessage>,
+}
+
+/// Odkaz v drobečkové navigaci stromu témat
+#[derive(Debug, Serialize)]
+pub struct MqttTopicCrumb {
+    pub name: String,
+    pub topic: String,
+}
+
+/// Zpráva posílaná do WebSocketu živého výpisu (/api/v1/mqtt/live)
+#[derive(Debug, Serialize)]
+pub struct MqttLiveMessage {
+    pub topic: String,
+    // Dekódovaný payload, zkrácený na 'MAX_LIVE_PAYLOAD' znaků
+    pub payload: String,
+    pub decoder: String,
+    pub decode_error: Option<String>,
+    pub payload_size: usize,
+    pub received_at: DateTime<Utc>,
+    pub qos: u8,
+    pub retain: bool,
+}
+
+/// Události, které server posílá klientovi živého výpisu
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MqttLiveEvent {
+    Message(MqttLiveMessage),
+    // Potvrzení aktivního filtru (po připojení a po každé změně)
+    Filter { filter: String },
+    Paused { paused: bool },
+    // Klient nestíhal číst, 'skipped' zpráv se zahodilo
+    Lagged { skipped: u64 },
+    Error { error: String },
+}
+
+/// Příkazy, které klient živého výpisu posílá serveru
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MqttLiveCommand {
+    Filter { filter: String },
+    Pause,
+    Resume,
+}
+
+/// View Model pro 'mqtt_live.html' šablonu
+#[derive(Serialize)]
+pub struct MqttLiveTemplate {
+    // Filtr, se kterým se stránka připojí
+    pub filter: String,
+    pub subscriptions: Vec<String>,
+    pub unavailable: Option<UnavailableTemplate>,
+}
+
+/// View Model pro 'mqtt_topics.html' šablonu
+#[derive(Serialize)]
+pub struct MqttTopicsTemplate {
+    pub prefix: String,
+    pub breadcrumbs: Vec<MqttTopicCrumb>,
+    pub rows: Vec<MqttTopicRow>,
+    pub truncated: bool,
+    // Detail zprávy na tématu 'prefix' (celý payload)
+    pub selected: Option<MqttTopicMessage>,
+    pub subscriptions: Vec<String>,
+    pub topic_count: usize,
+    pub max_topics: usize,
+    pub dropped: u64,
+    pub unavailable: Option<UnavailableTemplate>,
 }
 
 /// Reprezentuje jeden 'hit' (záznam) ve výsledcích hledání
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ManticoreHit {
+    // Starší verze Manticore posílají '_id' jako řetězec, novější jako číslo
+    #[serde(default)]
+    pub _id: serde_json::Value,
+    #[serde(default)]
+    pub _score: Option<f64>,
     // Manticore vrací data v '_source' poli
     pub _source: serde_json::Value, // Použijeme generickou JSON hodnotu
+    // Zvýrazněné úryvky podle pole (jen když jsme o ně požádali)
+    #[serde(default)]
+    pub highlight: BTreeMap<String, Vec<String>>,
 }
 
 /// Reprezentuje obálku 'hits', kterou Manticore posílá
@@ -80,20 +706,265 @@ pub struct ManticoreHits {
     pub hits: Vec<ManticoreHit>,
 }
 
+/// Jeden 'bucket' agregace: hodnota atributu a počet dokumentů
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ManticoreBucket {
+    pub key: serde_json::Value,
+    pub doc_count: u64,
+}
+
+/// Výsledek jedné agregace ('aggs') podle jejího názvu
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ManticoreAggregation {
+    #[serde(default)]
+    pub buckets: Vec<ManticoreBucket>,
+}
+
 /// Reprezentuje celou odpověď z Manticore Search
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ManticoreResponse {
     pub hits: ManticoreHits,
+    // Jen když dotaz obsahoval 'aggs'
+    #[serde(default)]
+    pub aggregations: BTreeMap<String, ManticoreAggregation>,
+}
+
+/// Jedna sada výsledků z SQL endpointu ('/sql?mode=raw')
+#[derive(Debug, Deserialize)]
+pub struct ManticoreSqlResult {
+    #[serde(default)]
+    pub data: Vec<serde_json::Map<String, serde_json::Value>>,
+    // Chyba příkazu (prázdná = bez chyby)
+    #[serde(default)]
+    pub error: String,
+}
+
+/// Tabulka z 'SHOW TABLES' doplněná o 'SHOW TABLE STATUS'
+#[derive(Debug, Serialize)]
+pub struct ManticoreTableInfo {
+    pub name: String,
+    // 'rt', 'percolate', 'distributed'...
+    pub kind: String,
+    // 'None' = stav se nepodařilo načíst (např. distribuovaná tabulka)
+    pub documents: Option<u64>,
+    pub ram_bytes: Option<u64>,
+    pub disk_bytes: Option<u64>,
+}
+
+/// Sloupec tabulky z 'DESCRIBE'
+#[derive(Debug, Serialize)]
+pub struct ManticoreColumn {
+    pub field: String,
+    pub kind: String,
+    // Např. 'indexed stored' u textu, 'columnar' u atributů
+    pub properties: String,
+}
+
+/// View Model pro 'search_admin.html' šablonu
+#[derive(Serialize)]
+pub struct SearchAdminTemplate {
+    pub tables: Vec<ManticoreTableInfo>,
+    // Detail vybrané tabulky (schéma a celý 'SHOW TABLE STATUS')
+    pub selected: Option<String>,
+    pub columns: Vec<ManticoreColumn>,
+    pub status: Vec<(String, String)>,
+    // Typy sloupců nabízené ve formuláři pro novou tabulku
+    pub column_types: Vec<&'static str>,
+    // Hodnoty formuláře pro novou tabulku (po neúspěšném vytvoření)
+    pub create_name: String,
+    pub create_schema: String,
+    // Výsledek poslední akce
+    pub notice: Option<String>,
+    pub error: Option<String>,
+    // Vyplněno, pokud Manticore neodpověděl
+    pub unavailable: Option<UnavailableTemplate>,
+}
+
+/// Kus textu úryvku; 'mark' = shoda s dotazem
+#[derive(Debug, Serialize)]
+pub struct HighlightPart {
+    pub text: String,
+    pub mark: bool,
+}
+
+/// Jeden zvýrazněný úryvek z pole dokumentu
+#[derive(Debug, Serialize)]
+pub struct SearchSnippet {
+    pub field: String,
+    pub parts: Vec<HighlightPart>,
+}
+
+/// Jeden výsledek hledání připravený pro kartu v 'search.html'
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub id: String,
+    pub score: Option<f64>,
+    pub title: Vec<HighlightPart>,
+    pub snippets: Vec<SearchSnippet>,
+    // Celý dokument pro zobrazení "JSON"
+    pub source: serde_json::Value,
+}
+
+/// Hodnota facetu v postranním panelu. 'href' hodnotu jako filtr
+/// přidá, u aktivní ('active') ji naopak odebere.
+#[derive(Debug, Serialize)]
+pub struct SearchFacetBucket {
+    pub label: String,
+    pub count: u64,
+    pub active: bool,
+    pub href: String,
+}
+
+/// Jeden facet (atribut) v postranním panelu
+#[derive(Debug, Serialize)]
+pub struct SearchFacet {
+    pub field: String,
+    pub buckets: Vec<SearchFacetBucket>,
+}
+
+/// Aktivní filtr s odkazem na jeho zrušení
+#[derive(Debug, Serialize)]
+pub struct SearchActiveFilter {
+    pub field: String,
+    pub label: String,
+    // Hodnota parametru 'filter' (pro skrytá pole formuláře)
+    pub param: String,
+    pub remove_href: String,
+}
+
+/// Pole pokročilého režimu hledání (/search?mode=advanced).
+/// Slouží jako query parametry i jako hodnoty formuláře,
+/// prázdný řetězec = nevyplněno.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SearchAdvancedForm {
+    // Pole pro fulltext oddělená čárkou (prázdné = všechna)
+    pub fields: String,
+    // Všechna slova / přesná fráze / žádné ze slov
+    pub all: String,
+    pub phrase: String,
+    pub none: String,
+    // Dotaz v syntaxi Manticore ('@title senzor -chyba')
+    pub query_string: String,
+    // Rozsah atributu včetně obou mezí
+    pub range_field: String,
+    pub range_gte: String,
+    pub range_lte: String,
+    // Vzdálenost od bodu; 'geo_fields' = atributy "lat,lon"
+    pub geo_fields: String,
+    pub geo_lat: String,
+    pub geo_lon: String,
+    pub geo_km: String,
 }
 
 /// View Model pro 'search.html' šablonu
 #[derive(Serialize)]
 pub struct SearchTemplate {
     pub query: String, // Co uživatel hledal
+    // Vyplněno v pokročilém režimu; 'query_json' je sestavený dotaz
+    pub advanced: Option<SearchAdvancedForm>,
+    pub query_json: Option<String>,
+    // Chyby v polích pokročilého režimu (hledání se pak nespustí)
+    pub errors: Vec<String>,
+    // Prohledávaný index a všechny dostupné (z 'SHOW TABLES')
+    pub index: String,
+    pub indexes: Vec<String>,
+    // Řazení ve tvaru 'pole' / '-pole', prázdné = podle relevance
+    pub sort: String,
+    pub page: u32,
+    pub per_page: u32,
+    pub per_page_options: Vec<u32>,
     // ZMĚNA: Použijeme i64.
     // -1 bude znamenat "nevyhledáno"
     // -2 bude znamenat "chyba"
     // >= 0 bude znamenat počet výsledků
     pub total_hits: i64,
-    pub results: Vec<serde_json::Value>, // Jen 'rozbalená' data
-}
\ No newline at end of file
+    pub total_pages: u32,
+    // Odkazy na stránky, 'None' = vynechané stránky ("…");
+    // 'page_link' je odkaz bez čísla stránky na konci
+    pub pages: Vec<Option<u32>>,
+    pub page_link: String,
+    pub facets: Vec<SearchFacet>,
+    pub filters: Vec<SearchActiveFilter>,
+    // Výsledků je víc, než Manticore dovolí prostránkovat ('MAX_MATCHES')
+    pub capped: bool,
+    pub results: Vec<SearchResult>,
+    // Vyplněno, pokud Manticore neodpověděl
+    pub unavailable: Option<UnavailableTemplate>,
+    // Stav indexeru ('None' = vypnutý)
+    pub indexer: Option<IndexerStats>,
+}
+/// Stav jedné závislosti (backendu) z pohledu health kontroly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+/// Výsledek jedné sondy (Postgres, Valkey, Manticore, MQTT)
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyHealth {
+    pub name: String,
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    pub detail: String,
+}
+
+/// Souhrnný výsledek všech sond - vrací '/readyz' a zobrazuje '/health'
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub checked_at: DateTime<Utc>,
+    pub dependencies: Vec<DependencyHealth>,
+}
+
+/// View Model pro 'health.html' šablonu
+#[derive(Serialize)]
+pub struct HealthTemplate {
+    pub app_name: String,
+    pub report: HealthReport,
+}
+
+/// Stav připojení k jednomu backendu (Postgres, Valkey...).
+///
+/// Drží ho 'state::Backend' a zobrazuje se v panelu "služba nedostupná".
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendStatus {
+    pub connected: bool,
+    // Od kdy je backend v aktuálním stavu
+    pub since: DateTime<Utc>,
+    pub last_error: Option<String>,
+    // Počet neúspěšných pokusů od posledního úspěšného připojení
+    pub attempts: u32,
+    pub next_retry_at: Option<DateTime<Utc>>,
+}
+
+impl BackendStatus {
+    /// Krátký lidsky čitelný popis stavu (pro logy a health report)
+    pub fn summary(&self) -> String {
+        if self.connected {
+            return "Připojeno".to_string();
+        }
+        let error = self.last_error.as_deref().unwrap_or("Připojuji...");
+        match self.next_retry_at {
+            Some(at) => format!(
+                "Nepřipojeno ({}), další pokus v {}",
+                error,
+                at.format("%H:%M:%S")
+            ),
+            None => format!("Nepřipojeno ({})", error),
+        }
+    }
+}
+
+/// View Model pro panel "služba nedostupná" ('unavailable_panel.html').
+///
+/// Buď se vykreslí jako samostatná stránka ('unavailable.html'),
+/// nebo se vloží do stránky, která umí fungovat i bez backendu.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnavailableTemplate {
+    pub service: String,
+    pub detail: String,
+    pub status: Option<BackendStatus>,
+}
@@ -1,4 +1,18 @@
-use axum::{extract::State, response::Json, response::IntoResponse};
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Json, response::IntoResponse};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::stream::{self, Stream, StreamExt};
+use chrono::{Duration, Utc};
+use crate::models::{BucketInterval, HealthStatus, MqttLiveCommand, MqttLiveEvent, MqttLiveMessage, MqttPublishRequest, MqttStatsHistoryResponse, SenzorAggregateResponse};
+use crate::routes::common::{load_valkey_key, load_valkey_server, parse_datetime_param, ValkeyKeyParams};
+use crate::services::{db_postgres, health, mqtt_client, mqtt_topics};
+use crate::services::mqtt_client::LiveMessage;
+use crate::services::mqtt_decode::DecoderRegistry;
+use crate::services::mqtt_topics::TopicFilter;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use serde::Deserialize;
+use serde_json::json;
 use crate::state::AppState; // <-- Změna
 
 /// API endpoint, který vrací aktuální MQTT statistiky jako JSON
@@ -10,12 +24,423 @@ pub async fn get_mqtt_stats(
     State(app_state): State<AppState>
 ) -> impl IntoResponse {
     
-    // Získáme zámek pro čtení
-    let stats_lock = app_state.mqtt_stats.read().await;
-    
-    // Klonujeme data (aby zámek mohl být hned uvolněn)
-    let stats_data = stats_lock.clone();
+    // Klonujeme data (aby zámek kanálu mohl být hned uvolněn)
+    let stats_data = app_state.mqtt_stats.borrow().clone();
     
     // Vrátíme JSON
     Json(stats_data).into_response()
-}
\ No newline at end of file
+}
+
+/// Jak dlouho po změně čekáme, než pošleme SSE událost.
+/// Broker posílá $SYS hodnoty v dávkách (desítky témat najednou),
+/// takže z jedné dávky pošleme jen jednu událost.
+const SSE_COALESCE: std::time::Duration = std::time::Duration::from_millis(250);
+/// Interval keep-alive komentářů, aby proxy nezavřela nečinné spojení
+const SSE_KEEP_ALIVE: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Zaloguje konec SSE spojení (klient odešel nebo zanikl sběrač)
+struct SseGuard;
+
+impl Drop for SseGuard {
+    fn drop(&mut self) {
+        tracing::info!("MQTT: Odběratel SSE streamu statistik se odpojil");
+    }
+}
+
+/// Živý stream MQTT statistik ('/api/v1/mqtt/stats/stream', Server-Sent Events).
+///
+/// Hned po připojení pošle aktuální stav, pak událost 'stats' při každé
+/// změně. Stream skončí, když klient zavře spojení (axum ho zahodí),
+/// nebo když zanikne sběrač (uzavřený 'watch' kanál).
+pub async fn get_mqtt_stats_stream(
+    State(app_state): State<AppState>
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    tracing::info!("MQTT: Nový odběratel SSE streamu statistik");
+
+    let mut rx = app_state.mqtt_stats.clone();
+    let initial = rx.borrow_and_update().clone();
+
+    // 'guard' žije ve stavu streamu - zaloguje odpojení, až axum stream zahodí
+    let updates = stream::unfold((rx, SseGuard), |(mut rx, guard)| async move {
+        // 'Err' = odesílatel zanikl, stream čistě ukončíme
+        rx.changed().await.ok()?;
+        tokio::time::sleep(SSE_COALESCE).await;
+        let stats = rx.borrow_and_update().clone();
+        Some((Event::default().event("stats").json_data(stats), (rx, guard)))
+    });
+    let events = stream::once(async move { Event::default().event("stats").json_data(initial) })
+        .chain(updates);
+
+    Sse::new(events).keep_alive(KeepAlive::new().interval(SSE_KEEP_ALIVE).text("keep-alive"))
+}
+
+/// Jak často posíláme do WebSocketu živého výpisu ping,
+/// aby proxy nezavřela spojení, na kterém zrovna nic nechodí
+const LIVE_PING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Query parametry pro '/api/v1/mqtt/live' (?filter=sensors/%2B/temp)
+#[derive(Deserialize)]
+pub struct MqttLiveParams {
+    #[serde(default = "default_live_filter")]
+    pub filter: String,
+}
+
+fn default_live_filter() -> String {
+    "#".to_string()
+}
+
+/// WebSocket živého výpisu MQTT zpráv ('/api/v1/mqtt/live').
+///
+/// Posílá zprávy, které přijme sběrač a které odpovídají filtru
+/// klienta (jen v rámci témat, která sběrač odebírá). Klient může
+/// filtr měnit a výpis pozastavit příkazy 'MqttLiveCommand'.
+pub async fn get_mqtt_live(
+    ws: WebSocketUpgrade,
+    State(app_state): State<AppState>,
+    Query(params): Query<MqttLiveParams>,
+) -> impl IntoResponse {
+    let filter = match TopicFilter::parse(&params.filter) {
+        Ok(filter) => filter,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "errors": [e] }))).into_response(),
+    };
+
+    // Odebírat začneme hned, ať nepřijdeme o zprávy během handshake
+    let rx = app_state.mqtt_live.subscribe();
+    tracing::info!("MQTT: Nový klient živého výpisu (filtr '{}')", filter.as_str());
+    let decoders = app_state.mqtt_decoders.clone();
+    ws.on_upgrade(move |socket| mqtt_live_session(socket, rx, filter, decoders))
+        .into_response()
+}
+
+/// Obsluha jednoho WebSocket klienta živého výpisu.
+///
+/// Každý klient má vlastního příjemce 'broadcast' kanálu. Pomalý
+/// klient tak nebrzdí sběrač ani ostatní - jen sám přijde o nejstarší
+/// zprávy a dostane událost 'lagged' s jejich počtem.
+async fn mqtt_live_session(
+    mut socket: WebSocket,
+    mut rx: broadcast::Receiver<Arc<LiveMessage>>,
+    mut filter: TopicFilter,
+    decoders: Arc<DecoderRegistry>,
+) {
+    let mut paused = false;
+    let mut ping = tokio::time::interval(LIVE_PING_INTERVAL);
+    ping.tick().await; // první tik je okamžitý
+
+    let mut result = send_live(&mut socket, &MqttLiveEvent::Filter {
+        filter: filter.as_str().to_string(),
+    }).await;
+
+    while result.is_ok() {
+        result = tokio::select! {
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    let event = match serde_json::from_str::<MqttLiveCommand>(&text) {
+                        Ok(MqttLiveCommand::Filter { filter: requested }) => match TopicFilter::parse(&requested) {
+                            Ok(parsed) => {
+                                filter = parsed;
+                                MqttLiveEvent::Filter { filter: requested }
+                            }
+                            Err(error) => MqttLiveEvent::Error { error },
+                        },
+                        Ok(MqttLiveCommand::Pause) => {
+                            paused = true;
+                            MqttLiveEvent::Paused { paused }
+                        }
+                        Ok(MqttLiveCommand::Resume) => {
+                            paused = false;
+                            MqttLiveEvent::Paused { paused }
+                        }
+                        Err(e) => MqttLiveEvent::Error { error: format!("Neznámý příkaz: {}", e) },
+                    };
+                    send_live(&mut socket, &event).await
+                }
+                // Na ping odpovídá axum sám, binární zprávy nečekáme
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => Ok(()),
+            },
+            received = rx.recv() => match received {
+                // Pozastavený klient zprávy dál odebírá (aby nezaostal), jen je zahazuje
+                Ok(message) if paused || !filter.matches(&message.packet.topic) => Ok(()),
+                Ok(message) => send_live(&mut socket, &MqttLiveEvent::Message(live_view(&message, &decoders))).await,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!("MQTT: Klient živého výpisu nestíhá, zahozeno {} zpráv", skipped);
+                    send_live(&mut socket, &MqttLiveEvent::Lagged { skipped }).await
+                }
+                // Sběrač zanikl, další zprávy už nepřijdou
+                Err(RecvError::Closed) => break,
+            },
+            _ = ping.tick() => socket.send(Message::Ping(Vec::new())).await,
+        };
+    }
+
+    tracing::info!("MQTT: Klient živého výpisu se odpojil (filtr '{}')", filter.as_str());
+}
+
+/// Serializuje událost do textové WebSocket zprávy
+async fn send_live(socket: &mut WebSocket, event: &MqttLiveEvent) -> Result<(), axum::Error> {
+    match serde_json::to_string(event) {
+        Ok(text) => socket.send(Message::Text(text)).await,
+        Err(e) => {
+            tracing::error!("Chyba při serializaci události živého výpisu: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Převede přijatou zprávu na View Model pro klienta
+fn live_view(message: &LiveMessage, decoders: &DecoderRegistry) -> MqttLiveMessage {
+    let packet = &message.packet;
+    // Obří payload nedekódujeme celý
+    let stored = &packet.payload[..packet.payload.len().min(mqtt_topics::MAX_LIVE_PAYLOAD)];
+    let decoded = decoders.decode(&packet.topic, stored);
+    MqttLiveMessage {
+        topic: packet.topic.clone(),
+        payload: mqtt_topics::preview(&decoded.text, mqtt_topics::MAX_LIVE_PAYLOAD, stored.len() < packet.payload.len()),
+        decoder: decoded.decoder.to_string(),
+        decode_error: decoded.error,
+        payload_size: packet.payload.len(),
+        received_at: message.received_at,
+        qos: packet.qos,
+        retain: packet.retain,
+    }
+}
+
+/// Query parametry pro '/api/v1/mqtt/stats/history' (?minutes=15)
+#[derive(Deserialize)]
+pub struct MqttHistoryParams {
+    // Jen posledních N minut (bez parametru celá historie)
+    pub minutes: Option<i64>,
+}
+
+/// API endpoint s historií statistik brokeru ('/api/v1/mqtt/stats/history').
+///
+/// Vrací vzorky z kruhového bufferu v paměti, od nejstaršího.
+pub async fn get_mqtt_stats_history(
+    State(app_state): State<AppState>,
+    Query(params): Query<MqttHistoryParams>,
+) -> impl IntoResponse {
+    if params.minutes.is_some_and(|m| m <= 0) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "errors": ["Parametr 'minutes' musí být kladné číslo"] })),
+        ).into_response();
+    }
+
+    // Starší vzorky v bufferu stejně nejsou a s velkým
+    // číslem by výpočet času přetekl
+    let max_minutes = app_state.config.mqtt.history_minutes as i64;
+    let since = params.minutes.map(|m| Utc::now() - Duration::minutes(m.min(max_minutes)));
+    let history = app_state.mqtt_history.read().await;
+    Json(MqttStatsHistoryResponse {
+        interval_secs: history.interval().as_secs(),
+        samples: history.since(since),
+    }).into_response()
+}
+
+/// API endpoint pro publikování MQTT zprávy ('POST /api/v1/mqtt/publish').
+///
+/// Zprávu odešle klient sběrače. Pokud sběrač nemá spojení
+/// s brokerem, vrací '503' - zpráva by se jinak ztratila.
+pub async fn post_mqtt_publish(
+    State(app_state): State<AppState>,
+    Json(request): Json<MqttPublishRequest>,
+) -> impl IntoResponse {
+    let errors = mqtt_client::validate_publish(&request, app_state.config.mqtt.max_packet_size);
+    if !errors.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "errors": errors }))).into_response();
+    }
+
+    if let Err(detail) = health::probe_mqtt(&app_state).await {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": format!("MQTT broker není dostupný: {}", detail) })),
+        ).into_response();
+    }
+
+    match mqtt_client::publish(&app_state.mqtt_client, &request) {
+        Ok(()) => {
+            tracing::info!(
+                "MQTT: publikováno na '{}' ({} B, QoS {}, retain {})",
+                request.topic, request.payload.len(), request.qos, request.retain
+            );
+            (StatusCode::ACCEPTED, Json(json!({ "status": "queued" }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("MQTT: publikování na '{}' selhalo: {}", request.topic, e);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "error": format!("Zprávu nelze odeslat: {}", e) })),
+            ).into_response()
+        }
+    }
+}
+
+/// Liveness sonda pro Kubernetes ('/healthz').
+///
+/// Záměrně netestuje žádné backendy - pokud proces odpovídá,
+/// je živý. Výpadek databáze nemá vést k restartu podu.
+pub async fn get_healthz() -> impl IntoResponse {
+    (StatusCode::OK, "ok")
+}
+
+/// Readiness sonda pro Kubernetes ('/readyz').
+///
+/// Otestuje všechny backendy a vrátí JSON report.
+/// Pokud některý backend neodpovídá, vrací '503 Service Unavailable'.
+pub async fn get_readyz(
+    State(app_state): State<AppState>
+) -> impl IntoResponse {
+    let report = health::check_all(&app_state).await;
+
+    let status_code = match report.status {
+        HealthStatus::Up => StatusCode::OK,
+        HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status_code, Json(report)).into_response()
+}
+
+/// Maximální počet kbelíků v jedné odpovědi agregačního API
+const AGGREGATE_MAX_BUCKETS: i64 = 10_000;
+
+/// Query parametry pro '/api/v1/sensors/:id/aggregate'
+/// (např. ?interval=5m&from=2025-11-01T00:00:00Z&to=2025-11-02T00:00:00Z)
+#[derive(Debug, Deserialize)]
+pub struct AggregateParams {
+    interval: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+/// API endpoint, který vrací agregovaná data jednoho senzoru.
+///
+/// Výchozí interval je 5 minut, výchozí rozsah posledních 24 hodin.
+pub async fn get_sensor_aggregate(
+    State(app_state): State<AppState>,
+    Path(senzor_id): Path<String>,
+    Query(params): Query<AggregateParams>,
+) -> impl IntoResponse {
+    // 1. Zvalidujeme parametry (všechny chyby najednou)
+    let mut errors = Vec::new();
+
+    let interval = params.interval.as_deref().unwrap_or("5m").parse::<BucketInterval>();
+    let to = parse_datetime_param("to", params.to.as_deref(), &mut errors)
+        .unwrap_or_else(Utc::now);
+    let from = parse_datetime_param("from", params.from.as_deref(), &mut errors)
+        .unwrap_or_else(|| to - Duration::hours(24));
+
+    if from >= to {
+        errors.push("Začátek rozsahu ('from') musí být před koncem ('to')".to_string());
+    } else if let Ok(interval) = interval
+        && (to - from).num_seconds() / interval.duration().num_seconds() > AGGREGATE_MAX_BUCKETS
+    {
+        errors.push(format!(
+            "Rozsah je pro interval {} příliš velký (max {} kbelíků)",
+            interval.as_pg_interval(), AGGREGATE_MAX_BUCKETS
+        ));
+    }
+
+    let interval = match interval {
+        Ok(interval) if errors.is_empty() => interval,
+        Ok(_) => return (StatusCode::BAD_REQUEST, Json(json!({ "errors": errors }))).into_response(),
+        Err(e) => {
+            errors.insert(0, e);
+            return (StatusCode::BAD_REQUEST, Json(json!({ "errors": errors }))).into_response();
+        }
+    };
+
+    // 2. Bez databáze vrátíme 503
+    let pool = match app_state.postgres_pool.get().await {
+        Ok(pool) => pool,
+        Err(status) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "error": status.summary() })),
+            ).into_response();
+        }
+    };
+
+    // 3. Zavoláme servisní vrstvu
+    match db_postgres::get_aggregated_timeseries(&pool, &senzor_id, interval, from, to).await {
+        Ok(buckets) => Json(SenzorAggregateResponse {
+            senzor_id,
+            interval,
+            from,
+            to,
+            buckets,
+        }).into_response(),
+        Err(e) => {
+            tracing::error!("Chyba při agregaci dat senzoru '{}': {}", senzor_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Chyba při čtení z databáze: {}", e) })),
+            ).into_response()
+        }
+    }
+}
+
+/// API endpoint s hodnotou klíče z Valkey ('/api/v1/valkey/key?key=...').
+///
+/// Vrací stejná data jako stránka /valkey/key - typ, TTL a hodnotu
+/// podle typu klíče (včetně stránkování). Neexistující klíč = 404.
+/// Klíč, který není platné UTF-8, se předává jako hex v '?key_raw='.
+pub async fn get_valkey_key(
+    State(app_state): State<AppState>,
+    Query(params): Query<ValkeyKeyParams>,
+) -> impl IntoResponse {
+    let key = match params.raw_key() {
+        Ok(key) => key,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response(),
+    };
+
+    let client = match app_state.valkey_client.get().await {
+        Ok(client) => client,
+        Err(status) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "error": status.summary() })),
+            ).into_response();
+        }
+    };
+
+    match load_valkey_key(&client, &key, &params).await {
+        Ok(data) if data.info.is_none() => (StatusCode::NOT_FOUND, Json(data)).into_response(),
+        Ok(data) => Json(data).into_response(),
+        Err(e) => {
+            tracing::error!("Chyba při čtení klíče '{}' z Valkey: {}", String::from_utf8_lossy(&key), e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Chyba při čtení z Valkey: {}", e) })),
+            ).into_response()
+        }
+    }
+}
+
+/// API endpoint s přehledem Valkey serveru ('/api/v1/valkey/info').
+///
+/// Vrací typované INFO sekce, slowlog a seznam klientů.
+pub async fn get_valkey_info(
+    State(app_state): State<AppState>
+) -> impl IntoResponse {
+    let client = match app_state.valkey_client.get().await {
+        Ok(client) => client,
+        Err(status) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "error": status.summary() })),
+            ).into_response();
+        }
+    };
+
+    match load_valkey_server(&client).await {
+        Ok(data) => Json(data).into_response(),
+        Err(e) => {
+            tracing::error!("Chyba při čtení INFO z Valkey: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Chyba při čtení z Valkey: {}", e) })),
+            ).into_response()
+        }
+    }
+}
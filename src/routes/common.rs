@@ -0,0 +1,116 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::models::{ValkeyKeyTemplate, ValkeyPage, ValkeyServerTemplate};
+use crate::services::db_valkey;
+use redis::Client as ValkeyClient;
+use serde::Deserialize;
+
+/// Výchozí a maximální počet klíčů na jednu stránku SCAN
+pub(crate) const VALKEY_DEFAULT_COUNT: usize = 50;
+pub(crate) const VALKEY_MAX_COUNT: usize = 1000;
+
+/// Query parametry detailu klíče (klíč může obsahovat '/', proto ne v cestě).
+///
+/// Stránkovací parametry používá jen typ, kterého se týkají
+/// (viz 'ValkeyPage'). Sdílí je i JSON API '/api/v1/valkey/key'.
+#[derive(Debug, Deserialize)]
+pub struct ValkeyKeyParams {
+    #[serde(default)]
+    pub key: String,
+    // Klíč jako hex - pro klíče, které nejsou platné UTF-8
+    pub key_raw: Option<String>,
+    pub cursor: Option<u64>,
+    pub offset: Option<i64>,
+    pub from_id: Option<String>,
+    pub count: Option<usize>,
+}
+
+impl ValkeyKeyParams {
+    /// Klíč tak, jak je ve Valkey. 'key_raw' má přednost před 'key'.
+    pub fn raw_key(&self) -> Result<Vec<u8>, String> {
+        match self.key_raw.as_deref().filter(|raw| !raw.is_empty()) {
+            Some(raw) => db_valkey::parse_raw_key(raw)
+                .ok_or_else(|| format!("Parametr 'key_raw' není platný hex: '{}'", raw)),
+            None => Ok(self.key.as_bytes().to_vec()),
+        }
+    }
+
+    fn page(&self) -> ValkeyPage {
+        let count = self.count.unwrap_or(VALKEY_DEFAULT_COUNT).clamp(1, VALKEY_MAX_COUNT);
+        ValkeyPage {
+            cursor: self.cursor.unwrap_or(0),
+            // Shora omezený, aby 'offset + count' nepřeteklo
+            offset: self.offset.unwrap_or(0).clamp(0, i64::MAX - count as i64),
+            from_id: self.from_id.clone().filter(|id| !id.is_empty()),
+            count,
+        }
+    }
+}
+
+/// Načte informace o klíči a jeho hodnotu (podle typu).
+/// Používá ho stránka /valkey/key i JSON API.
+pub(crate) async fn load_valkey_key(
+    client: &ValkeyClient,
+    key: &[u8],
+    params: &ValkeyKeyParams,
+) -> redis::RedisResult<ValkeyKeyTemplate> {
+    let page = params.page();
+
+    // 1. Zjistíme typ, TTL a velikost klíče
+    let info = db_valkey::get_key_info(client, key).await?;
+
+    // 2. Hodnotu čteme příkazem odpovídajícím typu klíče
+    let value = match &info {
+        Some(info) => Some(db_valkey::get_key_value(client, key, &info.key_type, &page).await?),
+        None => None,
+    };
+
+    Ok(ValkeyKeyTemplate {
+        key: String::from_utf8_lossy(key).into_owned(),
+        key_raw: db_valkey::raw_key_param(key),
+        info,
+        value,
+        count: page.count,
+    })
+}
+
+/// Kolik záznamů ze 'SLOWLOG' zobrazujeme
+const VALKEY_SLOWLOG_COUNT: usize = 25;
+
+/// Načte přehled serveru - INFO, slowlog a připojené klienty.
+/// Používá ho stránka /valkey/server i JSON API.
+pub(crate) async fn load_valkey_server(
+    client: &ValkeyClient,
+) -> redis::RedisResult<ValkeyServerTemplate> {
+    let (info, slowlog, clients) = tokio::try_join!(
+        db_valkey::get_server_info(client),
+        db_valkey::get_slowlog(client, VALKEY_SLOWLOG_COUNT),
+        db_valkey::get_client_list(client),
+    )?;
+
+    Ok(ValkeyServerTemplate { info, slowlog, clients })
+}
+
+/// Naparsuje čas z URL parametru.
+///
+/// Přijímá RFC 3339 ('2025-11-01T08:00:00Z') i formát z HTML
+/// '<input type="datetime-local">' ('2025-11-01T08:00'), který
+/// bereme jako UTC. Prázdná hodnota znamená "bez omezení".
+pub(crate) fn parse_datetime_param(
+    name: &str,
+    value: Option<&str>,
+    errors: &mut Vec<String>,
+) -> Option<DateTime<Utc>> {
+    let value = value.map(str::trim).filter(|v| !v.is_empty())?;
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(naive.and_utc());
+        }
+    }
+
+    errors.push(format!("Parametr '{}' není platný čas: '{}'", name, value));
+    None
+}
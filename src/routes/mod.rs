@@ -4,4 +4,7 @@
 // Klíčové slovo 'pub' je zde důležité, aby byly viditelné
 // z 'main.rs'.
 pub mod api;
+// 'common' drží načítání a validaci parametrů, které sdílí
+// HTML stránky ('web') i JSON API ('api').
+pub mod common;
 pub mod web;
@@ -1,12 +1,51 @@
-use axum::response::{Html, IntoResponse, Response};
-use axum::extract::{Query,State};
-use crate::models::{SearchTemplate};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::extract::{Path,Query,State};
+use axum::http::{header, HeaderMap};
+use axum::Form;
+use axum_extra::extract::Query as ExtraQuery;
+use crate::models::{ManticoreAggregation, SearchActiveFilter, SearchAdminTemplate, SearchAdvancedForm, SearchFacet, SearchFacetBucket, SearchTemplate};
+use crate::charts::{self, ChartOptions};
 use crate::state::AppState;       // Náš sdílený stav
 use tera::Context;               // <-- Potřebujeme Context pro Tera
-use crate::models::{IndexTemplate, PostgresTemplate, ValkeyTemplate,TimescaleTemplate,MqttTemplate}; 
-use crate::services::{db_postgres, db_valkey,search_manticore};
-use crate::state::MqttConfig;
+use crate::models::{IndexTemplate, PostgresTemplate, ValkeyTemplate,TimescaleTemplate,TimescaleFilter,MqttTemplate,MqttTopicsTemplate,MqttLiveTemplate,MqttTopicCrumb,HealthTemplate,UnavailableTemplate,BackendStatus}; 
+use crate::services::{db_postgres, db_valkey, health, mqtt_topics, search_manticore};
+// 'Query' je už extraktor z axum
+use crate::services::manticore_query::{BoolQuery, GeoDistance, Operator, Query as SearchQuery, RangeQuery};
+use crate::config::{FacetConfig, FacetKind, MqttConfig};
+use crate::routes::common::{load_valkey_key, load_valkey_server, parse_datetime_param, ValkeyKeyParams, VALKEY_DEFAULT_COUNT, VALKEY_MAX_COUNT};
 use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Vykreslí stránku s panelem "služba nedostupná" (HTTP 503).
+///
+/// Používají ji handlery, které bez svého backendu nemají co zobrazit.
+fn render_unavailable(
+    state: &AppState,
+    service: &str,
+    status: BackendStatus,
+) -> Response {
+    tracing::warn!("{} není dostupný: {}", service, status.summary());
+
+    let unavailable = UnavailableTemplate {
+        service: service.to_string(),
+        detail: status.summary(),
+        status: Some(status),
+    };
+
+    let mut context = Context::new();
+    context.insert("unavailable", &unavailable);
+
+    match state.tera.render("unavailable.html", &context) {
+        Ok(html) => (axum::http::StatusCode::SERVICE_UNAVAILABLE, Html(html)).into_response(),
+        Err(e) => {
+            tracing::error!("Chyba při renderování šablony 'unavailable.html': {}", e);
+            (
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                format!("Služba {} není dostupná", service)
+            ).into_response()
+        }
+    }
+}
 
 /// Handler pro hlavní stránku, nyní s 'Tera'
 pub async fn page_index(
@@ -57,8 +96,14 @@ pub async fn page_postgres(
 ) -> Response {
     tracing::info!("Obsloužen požadavek na /postgres");
 
+    // 0. Bez připojení k databázi zobrazíme jen panel "nedostupné"
+    let pool = match state.postgres_pool.get().await {
+        Ok(pool) => pool,
+        Err(status) => return render_unavailable(&state, "PostgreSQL", status),
+    };
+
     // 1. Zavoláme naši Servisní vrstvu (Model)
-    let data_result = db_postgres::get_data_from_postgres(&state.postgres_pool).await;
+    let data_result = db_postgres::get_data_from_postgres(&pool).await;
 
     // 2. Zpracujeme výsledek (data nebo chyba)
     let data = match data_result {
@@ -96,41 +141,82 @@ pub async fn page_postgres(
         }
     }
 }
+/// Query parametry prohlížeče klíčů (např. ?pattern=sensor:*&cursor=42&count=100)
+#[derive(Debug, Deserialize)]
+pub struct ValkeyBrowseParams {
+    pattern: Option<String>,
+    cursor: Option<String>,
+    count: Option<String>,
+}
+
+/// Handler pro stránku /valkey - prohlížeč klíčů přes 'SCAN'
 pub async fn page_valkey(
-    State(state): State<AppState>
+    State(state): State<AppState>,
+    Query(params): Query<ValkeyBrowseParams>,
 ) -> Response {
-    tracing::info!("Obsloužen požadavek na /valkey (K:V)");
+    tracing::info!("Obsloužen požadavek na /valkey ({:?})", params);
 
-    let key_to_fetch = "dashboard:status";
+    let client = match state.valkey_client.get().await {
+        Ok(client) => client,
+        Err(status) => return render_unavailable(&state, "Valkey", status),
+    };
 
-    // 1. Zavoláme novou servisní funkci
-    let value_option = match db_valkey::get_valkey_kv(&state.valkey_client, key_to_fetch).await {
-        Ok(value) => value, // 'value' je typu 'Option<String>'
-        Err(e) => {
-            tracing::error!("Neočekávaná chyba z Valkey service (GET): {}", e);
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Chyba při čtení z Valkey: {}", e)
-            ).into_response();
-        }
+    // 1. Zvalidujeme parametry
+    let mut errors = Vec::new();
+    let pattern = params.pattern
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| "*".to_string());
+    let cursor = match params.cursor.as_deref().filter(|c| !c.is_empty()) {
+        None => 0,
+        Some(raw) => raw.parse::<u64>().unwrap_or_else(|_| {
+            errors.push(format!("Kurzor musí být nezáporné číslo (zadáno '{}')", raw));
+            0
+        }),
+    };
+    let count = match params.count.as_deref().filter(|c| !c.is_empty()) {
+        None => VALKEY_DEFAULT_COUNT,
+        Some(raw) => match raw.parse::<usize>() {
+            Ok(count) if (1..=VALKEY_MAX_COUNT).contains(&count) => count,
+            _ => {
+                errors.push(format!("Počet musí být 1 až {} (zadáno '{}')", VALKEY_MAX_COUNT, raw));
+                VALKEY_DEFAULT_COUNT
+            }
+        },
     };
 
-    // 2. Zpracujeme 'Option'
-    // Pokud klíč neexistuje, 'value_option' bude 'None'.
-    let value_string = value_option
-        .unwrap_or_else(|| "(Klíč nebyl v databázi nalezen)".to_string());
+    // 2. Zavoláme servisní funkci (jen pokud jsou parametry v pořádku)
+    let (next_cursor, keys) = if errors.is_empty() {
+        match db_valkey::scan_keys(&client, cursor, &pattern, count).await {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::error!("Neočekávaná chyba z Valkey service (SCAN): {}", e);
+                return (
+                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Chyba při čtení z Valkey: {}", e)
+                ).into_response();
+            }
+        }
+    } else {
+        (0, vec![])
+    };
 
     // 3. Připravíme View Model
     let template_data = ValkeyTemplate {
-        key: key_to_fetch.to_string(),
-        value: value_string
+        pattern,
+        count,
+        cursor,
+        next_cursor,
+        keys,
+        status_key: state.config.valkey.status_key.clone(),
+        errors,
     };
 
     // 4. Vytvoříme 'tera::Context'
     let context = match Context::from_serialize(&template_data) {
         Ok(ctx) => ctx,
         Err(e) => {
-            tracing::error!("Chyba při serializaci Valkey (K:V) kontextu: {}", e);
+            tracing::error!("Chyba při serializaci Valkey kontextu: {}", e);
             return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response();
         }
     };
@@ -145,30 +231,209 @@ pub async fn page_valkey(
     }
 }
 
+/// Handler pro stránku /valkey/key?key=... - detail jednoho klíče
+pub async fn page_valkey_key(
+    State(state): State<AppState>,
+    Query(params): Query<ValkeyKeyParams>,
+) -> Response {
+    tracing::info!("Obsloužen požadavek na /valkey/key ({:?})", params);
 
-pub async fn page_timescale(
+    let key = match params.raw_key() {
+        Ok(key) => key,
+        Err(e) => return (axum::http::StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let client = match state.valkey_client.get().await {
+        Ok(client) => client,
+        Err(status) => return render_unavailable(&state, "Valkey", status),
+    };
+
+    // 1. Načteme klíč i hodnotu
+    let template_data = match load_valkey_key(&client, &key, &params).await {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Neočekávaná chyba z Valkey service (klíč '{}'): {}", String::from_utf8_lossy(&key), e);
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Chyba při čtení z Valkey: {}", e)
+            ).into_response();
+        }
+    };
+
+    // 2. Vytvoříme 'tera::Context'
+    let context = match Context::from_serialize(&template_data) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            tracing::error!("Chyba při serializaci Valkey (klíč) kontextu: {}", e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response();
+        }
+    };
+
+    // 3. Renderujeme šablonu
+    match state.tera.render("valkey_key.html", &context) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Chyba při renderování šablony 'valkey_key.html': {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response()
+        }
+    }
+}
+
+
+/// Handler pro stránku /valkey/server - paměť, persistence, slowlog...
+pub async fn page_valkey_server(
     State(state): State<AppState>
 ) -> Response {
-    tracing::info!("Obsloužen požadavek na /timescale");
+    tracing::info!("Obsloužen požadavek na /valkey/server");
 
-    // 1. Zavoláme novou servisní funkci
-    let senzory = match db_postgres::get_timeseries_from_postgres(&state.postgres_pool).await {
+    let client = match state.valkey_client.get().await {
+        Ok(client) => client,
+        Err(status) => return render_unavailable(&state, "Valkey", status),
+    };
+
+    // 1. Načteme data ze serveru
+    let template_data = match load_valkey_server(&client).await {
         Ok(data) => data,
         Err(e) => {
-            tracing::error!("Chyba při dotazu do TimescaleDB: {}", e);
+            tracing::error!("Neočekávaná chyba z Valkey service (INFO): {}", e);
             return (
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Chyba při čtení z databáze: {}", e)
+                format!("Chyba při čtení z Valkey: {}", e)
             ).into_response();
         }
     };
 
-    // 2. Připravíme View Model
+    // 2. Vytvoříme 'tera::Context'
+    let context = match Context::from_serialize(&template_data) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            tracing::error!("Chyba při serializaci Valkey (server) kontextu: {}", e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response();
+        }
+    };
+
+    // 3. Renderujeme šablonu
+    match state.tera.render("valkey_server.html", &context) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Chyba při renderování šablony 'valkey_server.html': {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response()
+        }
+    }
+}
+
+/// Výchozí a maximální počet řádků na stránce /timescale
+const TIMESCALE_DEFAULT_LIMIT: i64 = 100;
+const TIMESCALE_MAX_LIMIT: i64 = 10_000;
+/// Kolik senzorů lze vybrat najednou
+const TIMESCALE_MAX_SENZORY: usize = 50;
+
+/// Query parametry stránky /timescale
+/// (např. ?from=2025-11-01T08:00&senzor_id=a&senzor_id=b&limit=500)
+#[derive(Debug, Default, Deserialize)]
+pub struct TimescaleParams {
+    from: Option<String>,
+    to: Option<String>,
+    // Parametr se může opakovat, proto 'axum_extra::extract::Query'
+    #[serde(default)]
+    senzor_id: Vec<String>,
+    limit: Option<String>,
+}
+
+impl TimescaleParams {
+    /// Převede surové parametry na 'TimescaleFilter'.
+    /// Vrací všechny nalezené chyby najednou.
+    fn validate(&self) -> Result<TimescaleFilter, Vec<String>> {
+        let mut errors = Vec::new();
+
+        let from = parse_datetime_param("from", self.from.as_deref(), &mut errors);
+        let to = parse_datetime_param("to", self.to.as_deref(), &mut errors);
+        if let (Some(from), Some(to)) = (from, to) && from > to {
+            errors.push("Začátek rozsahu ('from') musí být před koncem ('to')".to_string());
+        }
+
+        let senzor_ids: Vec<String> = self.senzor_id
+            .iter()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+        if senzor_ids.len() > TIMESCALE_MAX_SENZORY {
+            errors.push(format!("Lze vybrat nejvýše {} senzorů", TIMESCALE_MAX_SENZORY));
+        }
+
+        let limit = match self.limit.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+            None => TIMESCALE_DEFAULT_LIMIT,
+            Some(raw) => match raw.parse::<i64>() {
+                Ok(limit) if (1..=TIMESCALE_MAX_LIMIT).contains(&limit) => limit,
+                _ => {
+                    errors.push(format!(
+                        "Limit musí být celé číslo 1 až {} (zadáno '{}')",
+                        TIMESCALE_MAX_LIMIT, raw
+                    ));
+                    TIMESCALE_DEFAULT_LIMIT
+                }
+            },
+        };
+
+        if errors.is_empty() {
+            Ok(TimescaleFilter { from, to, senzor_ids, limit })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Handler pro stránku /timescale s filtrem podle času a senzorů
+pub async fn page_timescale(
+    State(state): State<AppState>,
+    ExtraQuery(params): ExtraQuery<TimescaleParams>,
+) -> Response {
+    tracing::info!("Obsloužen požadavek na /timescale ({:?})", params);
+
+    let pool = match state.postgres_pool.get().await {
+        Ok(pool) => pool,
+        Err(status) => return render_unavailable(&state, "TimescaleDB", status),
+    };
+
+    // 1. Zvalidujeme parametry - při chybě dotaz vůbec nespustíme
+    let (senzory, limit, errors) = match params.validate() {
+        Ok(filter) => {
+            match db_postgres::get_timeseries_from_postgres(&pool, &filter).await {
+                Ok(data) => (data, filter.limit, vec![]),
+                Err(e) => {
+                    tracing::error!("Chyba při dotazu do TimescaleDB: {}", e);
+                    return (
+                        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Chyba při čtení z databáze: {}", e)
+                    ).into_response();
+                }
+            }
+        }
+        Err(errors) => (vec![], TIMESCALE_DEFAULT_LIMIT, errors),
+    };
+
+    // Seznam senzorů pro formulář - chyba zde není fatální
+    let available_ids = db_postgres::get_senzor_ids(&pool).await.unwrap_or_else(|e| {
+        tracing::warn!("Nepodařilo se načíst seznam senzorů: {}", e);
+        vec![]
+    });
+
+    // 2. Graf vykreslíme na serveru jako inline SVG (bez JavaScriptu)
+    let chart_svg = charts::render_line_chart(&senzory, &ChartOptions::default());
+
+    // 3. Připravíme View Model
     let template_data = TimescaleTemplate {
-        senzory: senzory
+        chart_svg,
+        senzory,
+        from: params.from.unwrap_or_default(),
+        to: params.to.unwrap_or_default(),
+        selected_ids: params.senzor_id,
+        limit,
+        available_ids,
+        errors,
     };
 
-    // 3. Vytvoříme 'tera::Context'
+    // 4. Vytvoříme 'tera::Context'
     let context = match Context::from_serialize(&template_data) {
         Ok(ctx) => ctx,
         Err(e) => {
@@ -177,7 +442,7 @@ pub async fn page_timescale(
         }
     };
 
-    // 4. Renderujeme šablonu
+    // 5. Renderujeme šablonu
     match state.tera.render("timescale.html", &context) {
         Ok(html) => Html(html).into_response(),
         Err(e) => {
@@ -187,6 +452,59 @@ pub async fn page_timescale(
     }
 }
 
+/// Výchozí počet bodů pro samostatný graf (víc než tabulka, graf je levný)
+const CHART_DEFAULT_LIMIT: &str = "1000";
+
+/// Handler pro '/charts/sensor/<id>.svg' - graf jednoho senzoru jako obrázek.
+///
+/// Přijímá stejné parametry jako /timescale ('from', 'to', 'limit').
+pub async fn chart_sensor_svg(
+    State(state): State<AppState>,
+    Path(file): Path<String>,
+    ExtraQuery(mut params): ExtraQuery<TimescaleParams>,
+) -> Response {
+    tracing::info!("Obsloužen požadavek na /charts/sensor/{}", file);
+
+    // axum neumí ':id.svg' v jednom segmentu, příponu ořízneme sami
+    let Some(senzor_id) = file.strip_suffix(".svg").filter(|id| !id.is_empty()) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Graf musí mít příponu .svg").into_response();
+    };
+
+    let pool = match state.postgres_pool.get().await {
+        Ok(pool) => pool,
+        Err(status) => {
+            return (axum::http::StatusCode::SERVICE_UNAVAILABLE, status.summary()).into_response();
+        }
+    };
+
+    params.senzor_id = vec![senzor_id.to_string()];
+    params.limit.get_or_insert_with(|| CHART_DEFAULT_LIMIT.to_string());
+    let filter = match params.validate() {
+        Ok(filter) => filter,
+        Err(errors) => {
+            return (axum::http::StatusCode::BAD_REQUEST, errors.join("\n")).into_response();
+        }
+    };
+
+    let data = match db_postgres::get_timeseries_from_postgres(&pool, &filter).await {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Chyba při dotazu do TimescaleDB: {}", e);
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Chyba při čtení z databáze: {}", e)
+            ).into_response();
+        }
+    };
+
+    let svg = charts::render_line_chart(&data, &ChartOptions {
+        title: Some(format!("Senzor {}", senzor_id)),
+        ..ChartOptions::default()
+    });
+
+    ([(axum::http::header::CONTENT_TYPE, "image/svg+xml")], svg).into_response()
+}
+
 pub async fn page_mqtt(
     // Bereme si 'MqttConfig' (kvůli 'host' a 'interval')
     State(mqtt_config): State<MqttConfig>,
@@ -197,9 +515,33 @@ pub async fn page_mqtt(
 
     // 1. Vytvoříme View Model POUZE s konfigurací.
     //    Data si načte JavaScript sám.
+    //    Pokud sběrač neběží, stránka zobrazí panel "nedostupné",
+    //    ale JavaScript dál zkouší načítat data.
+    let connection = state.mqtt_connection.read().await.clone();
+    let unavailable = health::probe_mqtt(&state).await.err().map(|detail| UnavailableTemplate {
+        service: "MQTT broker".to_string(),
+        detail,
+        status: (!connection.status.connected).then(|| connection.status.clone()),
+    });
+
+    let ingest = match &state.ingest_stats {
+        Some(stats) => Some(stats.read().await.clone()),
+        None => None,
+    };
+
     let template_data = MqttTemplate {
-        broker_host: mqtt_config.host,
+        broker_host: format!(
+            "{}://{}:{}",
+            if mqtt_config.tls.enabled { "mqtts" } else { "mqtt" },
+            mqtt_config.host,
+            mqtt_config.port
+        ),
+        protocol: mqtt_config.protocol.to_string(),
+        username: mqtt_config.username,
         refresh_interval_ms: mqtt_config.refresh_interval_ms,
+        unavailable,
+        connection,
+        ingest,
     };
 
     // 2. Vytvoříme 'tera::Context'
@@ -221,55 +563,514 @@ pub async fn page_mqtt(
     }
 }
 
+/// Maximální počet řádků stromu na stránce /mqtt/topics
+const MQTT_TOPICS_MAX_ROWS: usize = 1000;
+
+/// Query parametry pro /mqtt/topics (?topic=sensors/kuchyn)
+#[derive(Deserialize)]
+pub struct MqttTopicsParams {
+    // Podstrom, který chceme zobrazit. Prázdný = celý strom.
+    #[serde(default)]
+    pub topic: String,
+}
+
+/// Handler pro průzkumník MQTT témat (/mqtt/topics).
+///
+/// Zobrazuje strom témat, který plní sběrač na pozadí,
+/// a u vybraného tématu celý poslední payload.
+pub async fn page_mqtt_topics(
+    State(mqtt_config): State<MqttConfig>,
+    State(state): State<AppState>,
+    Query(params): Query<MqttTopicsParams>,
+) -> Response {
+    tracing::info!("Obsloužen požadavek na /mqtt/topics (téma '{}')", params.topic);
+
+    let unavailable = health::probe_mqtt(&state).await.err().map(|detail| UnavailableTemplate {
+        service: "MQTT broker".to_string(),
+        detail,
+        status: None,
+    });
+
+    // 1. Vytáhneme data ze stromu (zámek držíme jen po dobu kopírování)
+    let template_data = {
+        let tree = state.mqtt_topics.read().await;
+        let prefix = if tree.contains(&params.topic) { params.topic } else { String::new() };
+        let (rows, truncated) = tree.rows(&prefix, MQTT_TOPICS_MAX_ROWS, &state.mqtt_decoders);
+        let selected = tree
+            .get(&prefix)
+            .map(|m| mqtt_topics::message_view(&prefix, m, mqtt_topics::MAX_STORED_PAYLOAD, &state.mqtt_decoders));
+
+        let mut breadcrumbs = Vec::new();
+        if !prefix.is_empty() {
+            let mut path = String::new();
+            for level in prefix.split('/') {
+                if !breadcrumbs.is_empty() {
+                    path.push('/');
+                }
+                path.push_str(level);
+                breadcrumbs.push(MqttTopicCrumb { name: level.to_string(), topic: path.clone() });
+            }
+        }
+
+        MqttTopicsTemplate {
+            prefix,
+            breadcrumbs,
+            rows,
+            truncated,
+            selected,
+            subscriptions: mqtt_config.topics,
+            topic_count: tree.topic_count(),
+            max_topics: mqtt_config.max_topics,
+            dropped: tree.dropped(),
+            unavailable,
+        }
+    };
+
+    // 2. Vytvoříme 'tera::Context'
+    let context = match Context::from_serialize(&template_data) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            tracing::error!("Chyba při serializaci MQTT (topics) kontextu: {}", e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response();
+        }
+    };
+
+    // 3. Renderujeme šablonu
+    match state.tera.render("mqtt_topics.html", &context) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Chyba při renderování šablony 'mqtt_topics.html': {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response()
+        }
+    }
+}
+
+/// Query parametry pro /mqtt/live (?filter=sensors/%2B/temp)
+#[derive(Deserialize)]
+pub struct MqttLiveParams {
+    #[serde(default)]
+    pub filter: Option<String>,
+}
+
+/// Handler pro živý výpis MQTT zpráv (/mqtt/live).
+///
+/// Stránka samotná je jen kostra - zprávy jí posílá
+/// WebSocket '/api/v1/mqtt/live'.
+pub async fn page_mqtt_live(
+    State(mqtt_config): State<MqttConfig>,
+    State(state): State<AppState>,
+    Query(params): Query<MqttLiveParams>,
+) -> Response {
+    tracing::info!("Obsloužen požadavek na /mqtt/live");
+
+    let unavailable = health::probe_mqtt(&state).await.err().map(|detail| UnavailableTemplate {
+        service: "MQTT broker".to_string(),
+        detail,
+        status: None,
+    });
+
+    // 1. Připravíme data pro šablonu
+    let template_data = MqttLiveTemplate {
+        // Neplatný filtr by WebSocket odmítl, začneme raději se vším
+        filter: params
+            .filter
+            .filter(|f| mqtt_topics::validate_filter(f).is_ok())
+            .unwrap_or_else(|| "#".to_string()),
+        subscriptions: mqtt_config.topics,
+        unavailable,
+    };
+
+    // 2. Vytvoříme 'tera::Context'
+    let context = match Context::from_serialize(&template_data) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            tracing::error!("Chyba při serializaci MQTT (live) kontextu: {}", e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response();
+        }
+    };
+
+    // 3. Renderujeme šablonu
+    match state.tera.render("mqtt_live.html", &context) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Chyba při renderování šablony 'mqtt_live.html': {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response()
+        }
+    }
+}
+
 // ---- PŘIDÁNO PRO SEARCH ----
 
-/// Struct pro parsování URL query parametrů (např. ?q=test)
+/// Výchozí počet výsledků na stránku a nabídka v šabloně
+const SEARCH_DEFAULT_PER_PAGE: u32 = 20;
+const SEARCH_PER_PAGE_OPTIONS: [u32; 4] = [10, 20, 50, 100];
+/// Kolik stránek kolem aktuální ukázat v odkazech stránkování
+const SEARCH_PAGE_WINDOW: u32 = 2;
+
+/// Výchozí atributy se souřadnicemi pro hledání podle vzdálenosti
+const SEARCH_DEFAULT_GEO_FIELDS: (&str, &str) = ("lat", "lon");
+
+/// Struct pro parsování URL query parametrů
+/// (např. ?q=test&index=products&page=2&per_page=50&sort=-id&filter=host:rpi4)
 #[derive(Debug, Deserialize)]
 pub struct SearchParams {
     q: Option<String>, // 'q' je název našeho <input> pole
+    index: Option<String>,
+    page: Option<String>,
+    per_page: Option<String>,
+    // 'pole' = vzestupně, '-pole' = sestupně, prázdné = relevance
+    sort: Option<String>,
+    // Filtry z facetů 'pole:hodnota', může být víckrát
+    #[serde(default)]
+    filter: Vec<String>,
+    // 'advanced' = pokročilý režim s poli ze 'SearchAdvancedForm'
+    mode: Option<String>,
+    #[serde(flatten)]
+    advanced: SearchAdvancedForm,
 }
 
-/// Handler pro stránku /search
+impl SearchParams {
+    fn is_advanced(&self) -> bool {
+        self.mode.as_deref() == Some("advanced")
+    }
+
+    /// Sestaví dotaz z formuláře. V jednoduchém režimu je to 'match'
+    /// na všechna pole, v pokročilém kombinace všech vyplněných polí.
+    /// 'Ok(None)' = není co hledat. Vrací všechny chyby najednou.
+    fn query(&self) -> Result<Option<SearchQuery>, Vec<String>> {
+        let q = non_empty(self.q.as_deref().unwrap_or_default());
+        if !self.is_advanced() {
+            return Ok(q.map(|q| SearchQuery::matches(&[], q, Operator::Or)));
+        }
+
+        let form = &self.advanced;
+        let mut errors = Vec::new();
+        let fields: Vec<String> = form
+            .fields
+            .split(',')
+            .filter_map(non_empty)
+            .map(str::to_string)
+            .collect();
+        for field in &fields {
+            if let Err(e) = search_manticore::validate_field(field) {
+                errors.push(format!("Pole pro hledání: {}", e));
+            }
+        }
+
+        let mut query = BoolQuery::new();
+        if let Some(q) = q {
+            query = query.must(SearchQuery::matches(&fields, q, Operator::Or));
+        }
+        if let Some(all) = non_empty(&form.all) {
+            query = query.must(SearchQuery::matches(&fields, all, Operator::And));
+        }
+        if let Some(phrase) = non_empty(&form.phrase) {
+            query = query.must(SearchQuery::phrase(&fields, phrase));
+        }
+        if let Some(query_string) = non_empty(&form.query_string) {
+            query = query.must(SearchQuery::query_string(query_string));
+        }
+        if let Some(none) = non_empty(&form.none) {
+            query = query.must_not(SearchQuery::matches(&fields, none, Operator::Or));
+        }
+
+        if let Some(field) = non_empty(&form.range_field) {
+            if let Err(e) = search_manticore::validate_field(field) {
+                errors.push(format!("Rozsah: {}", e));
+            }
+            let gte = parse_number_param("Rozsah od", &form.range_gte, &mut errors);
+            let lte = parse_number_param("Rozsah do", &form.range_lte, &mut errors);
+            let mut range = RangeQuery::new(field);
+            if let Some(gte) = gte {
+                range = range.gte(gte);
+            }
+            if let Some(lte) = lte {
+                range = range.lte(lte);
+            }
+            if range.gte.is_none() && range.lte.is_none() {
+                errors.push("Rozsah potřebuje aspoň jednu mez ('od' nebo 'do')".to_string());
+            }
+            query = query.must(range);
+        }
+
+        if [&form.geo_lat, &form.geo_lon, &form.geo_km].iter().any(|v| non_empty(v).is_some())
+            && let Some(geo) = self.geo_distance(&mut errors)
+        {
+            query = query.must(geo);
+        }
 
+        if query.is_negative_only() {
+            errors.push("Samotné vyloučení slov nestačí, zadejte i co hledat".to_string());
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok((!query.is_empty()).then(|| query.build()))
+    }
+
+    /// Vzdálenost od bodu z polí 'geo_*'
+    fn geo_distance(&self, errors: &mut Vec<String>) -> Option<GeoDistance> {
+        let form = &self.advanced;
+        let (lat_field, lon_field) = match non_empty(&form.geo_fields) {
+            None => SEARCH_DEFAULT_GEO_FIELDS,
+            Some(fields) => match fields.split_once(',') {
+                Some((lat, lon)) => (lat.trim(), lon.trim()),
+                None => {
+                    errors.push(format!("Souřadnice: '{}' nemá tvar 'lat,lon'", fields));
+                    return None;
+                }
+            },
+        };
+        for field in [lat_field, lon_field] {
+            if let Err(e) = search_manticore::validate_field(field) {
+                errors.push(format!("Souřadnice: {}", e));
+            }
+        }
+
+        let mut coordinate = |name: &str, raw: &str, limit: f64| match raw.trim().parse::<f64>() {
+            Ok(value) if value.abs() <= limit => Some(value),
+            _ => {
+                errors.push(format!("{} musí být číslo v rozsahu ±{} (zadáno '{}')", name, limit, raw));
+                None
+            }
+        };
+        let lat = coordinate("Zeměpisná šířka", &form.geo_lat, 90.0);
+        let lon = coordinate("Zeměpisná délka", &form.geo_lon, 180.0);
+        let km = match form.geo_km.trim().parse::<f64>() {
+            Ok(km) if km > 0.0 && km.is_finite() => Some(km),
+            _ => {
+                errors.push(format!("Vzdálenost musí být kladné číslo v km (zadáno '{}')", form.geo_km));
+                None
+            }
+        };
+
+        Some(GeoDistance {
+            lat_field: lat_field.to_string(),
+            lon_field: lon_field.to_string(),
+            lat: lat?,
+            lon: lon?,
+            distance_m: km? * 1000.0,
+        })
+    }
+
+    /// Parametry, které určují dotaz (pro odkazy stránkování a facetů)
+    fn criteria(&self) -> Vec<(String, String)> {
+        let mut criteria = vec![("q".to_string(), self.q.clone().unwrap_or_default())];
+        if self.is_advanced() {
+            criteria.push(("mode".to_string(), "advanced".to_string()));
+            // Pole formuláře podle jejich názvů v query stringu
+            if let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(&self.advanced) {
+                criteria.extend(fields.into_iter().filter_map(|(name, value)| match value {
+                    serde_json::Value::String(value) if !value.is_empty() => Some((name, value)),
+                    _ => None,
+                }));
+            }
+        }
+        criteria
+    }
+}
+
+/// Oříznutá hodnota, nebo 'None' pro prázdnou
+fn non_empty(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|value| !value.is_empty())
+}
+
+/// Číslo z formuláře (celé zůstane celé). Prázdné = 'None'.
+fn parse_number_param(name: &str, raw: &str, errors: &mut Vec<String>) -> Option<serde_json::Value> {
+    let raw = non_empty(raw)?;
+    if let Ok(number) = raw.parse::<i64>() {
+        return Some(number.into());
+    }
+    match raw.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+        Some(number) => Some(number.into()),
+        None => {
+            errors.push(format!("{}: '{}' není číslo", name, raw));
+            None
+        }
+    }
+}
+
+/// Společná část odkazů na /search (stránkování, facety)
+struct SearchLink<'a> {
+    criteria: Vec<(String, String)>,
+    index: &'a str,
+    sort: &'a str,
+    per_page: u32,
+}
+
+impl SearchLink<'_> {
+    /// Odkaz na první stránku výsledků s danými filtry
+    fn href(&self, filters: &[search_manticore::SearchFilter]) -> String {
+        let mut url = form_urlencoded::Serializer::new(String::new());
+        url.extend_pairs(&self.criteria)
+            .append_pair("index", self.index)
+            .append_pair("sort", self.sort)
+            .append_pair("per_page", &self.per_page.to_string());
+        for filter in filters {
+            url.append_pair("filter", &filter.param());
+        }
+        format!("/search?{}", url.finish())
+    }
+}
+
+/// Handler pro stránku /search
 pub async fn page_search(
     State(state): State<AppState>,
-    Query(params): Query<SearchParams>,
+    ExtraQuery(params): ExtraQuery<SearchParams>,
 ) -> Response {
-    tracing::info!("Obsloužen požadavek na /search (query: {:?})", params.q);
+    tracing::info!("Obsloužen požadavek na /search ({:?})", params);
+
+    // 1. Seznam indexů pro výběr. Když 'SHOW TABLES' selže,
+    // nabídneme aspoň index z konfigurace (chybu ukáže hledání).
+    let default_index = state.config.manticore.index.clone();
+    let mut indexes = match search_manticore::list_tables(&state.manticore_client, &state.manticore_base_url).await {
+        Ok(indexes) => indexes,
+        Err(e) => {
+            tracing::warn!("Nelze načíst seznam indexů z Manticore: {}", e);
+            vec![]
+        }
+    };
+    if !indexes.contains(&default_index) {
+        indexes.insert(0, default_index.clone());
+    }
+    let index = params
+        .index
+        .clone()
+        .filter(|index| indexes.contains(index))
+        .unwrap_or(default_index);
+
+    // 2. Stránkování a řazení (neplatné hodnoty nahradí výchozí)
+    let per_page = params
+        .per_page
+        .as_deref()
+        .and_then(|value| value.parse::<u32>().ok())
+        .filter(|value| SEARCH_PER_PAGE_OPTIONS.contains(value))
+        .unwrap_or(SEARCH_DEFAULT_PER_PAGE);
+    // Za 'MAX_MATCHES' výsledků Manticore stránkovat nedovolí
+    let max_page = search_manticore::MAX_MATCHES.div_ceil(per_page);
+    let page = params
+        .page
+        .as_deref()
+        .and_then(|value| value.parse::<u32>().ok())
+        .unwrap_or(1)
+        .clamp(1, max_page);
+    let sort = params
+        .sort
+        .as_deref()
+        .filter(|sort| !sort.is_empty())
+        .and_then(|sort| sort.parse::<search_manticore::SearchSort>().ok());
+
+    // 3. Facety pro zvolený index a filtry z nich (neznámé zahodíme)
+    let facets: Vec<&FacetConfig> = state
+        .config
+        .manticore
+        .facets
+        .iter()
+        .filter(|facet| facet.index.as_ref().is_none_or(|only| *only == index))
+        .collect();
+    let mut filters: Vec<search_manticore::SearchFilter> = Vec::new();
+    for param in &params.filter {
+        match search_manticore::SearchFilter::parse(param, &facets) {
+            Ok(filter) if !filters.contains(&filter) => filters.push(filter),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Ignoruji filtr hledání '{}': {}", param, e),
+        }
+    }
+
+    // 4. Dotaz z formuláře (v pokročilém režimu může mít chyby)
+    let (query, errors) = match params.query() {
+        Ok(query) => (query, vec![]),
+        Err(errors) => (None, errors),
+    };
+
+    let sort_param = sort.as_ref().map(|sort| sort.to_string()).unwrap_or_default();
+    let link = SearchLink { criteria: params.criteria(), index: &index, sort: &sort_param, per_page };
+    let active_filters = filters
+        .iter()
+        .map(|filter| {
+            let rest: Vec<_> = filters.iter().filter(|other| *other != filter).cloned().collect();
+            SearchActiveFilter {
+                field: filter.field().to_string(),
+                label: filter.label(),
+                param: filter.param(),
+                remove_href: link.href(&rest),
+            }
+        })
+        .collect();
 
-    // 1. Připravíme výchozí data
     let mut template_data = SearchTemplate {
         query: params.q.clone().unwrap_or_default(),
+        advanced: params.is_advanced().then(|| params.advanced.clone()),
+        query_json: query
+            .as_ref()
+            .filter(|_| params.is_advanced())
+            .and_then(|query| serde_json::to_string_pretty(&query.to_json()).ok()),
+        errors,
+        index: index.clone(),
+        indexes,
+        sort: sort_param.clone(),
+        page,
+        per_page,
+        per_page_options: SEARCH_PER_PAGE_OPTIONS.to_vec(),
         total_hits: -1, // <-- ZMĚNA: -1 znamená "nevyhledáno"
+        total_pages: 0,
+        pages: vec![],
+        page_link: format!("{}&page=", link.href(&filters)),
+        facets: vec![],
+        filters: active_filters,
+        capped: false,
         results: vec![],
+        unavailable: None,
+        indexer: match &state.indexer_stats {
+            Some(stats) => Some(stats.read().await.clone()),
+            None => None,
+        },
     };
 
-    // 2. Pokud uživatel něco zadal do ?q=...
-    if let Some(query) = params.q {
-        if !query.is_empty() {
-            match search_manticore::search_manticore(
-                &state.manticore_client,
-                &state.manticore_base_url,
-                "dashboard_index",
-                &query
-            ).await {
-                Ok(response) => {
-                    // Úspěch - převedeme u32 na i64
-                    template_data.total_hits = response.hits.total as i64; // <-- ZMĚNA
-                    template_data.results = response.hits.hits
-                        .into_iter()
-                        .map(|hit| hit._source)
-                        .collect();
-                },
-                Err(e) => {
-                    tracing::error!("Chyba při dotazu do Manticore: {}", e);
-                    template_data.total_hits = -2; // <-- ZMĚNA: -2 znamená chyba
-                }
+    // 5. Pokud je co hledat
+    if let Some(query) = query {
+        let request = search_manticore::SearchRequest {
+            index: index.clone(),
+            query,
+            limit: per_page,
+            offset: (page - 1) * per_page,
+            sort,
+            highlight_fields: state.config.manticore.highlight_fields.clone(),
+            filters: filters.clone(),
+            facets: facets.iter().map(|facet| (*facet).clone()).collect(),
+        };
+        match search_manticore::search_manticore(
+            &state.manticore_client,
+            &state.manticore_base_url,
+            &request,
+        ).await {
+            Ok(response) => {
+                // Úspěch - převedeme u32 na i64
+                let total = response.hits.total;
+                template_data.total_hits = total as i64; // <-- ZMĚNA
+                template_data.capped = total > search_manticore::MAX_MATCHES;
+                template_data.total_pages = total.min(search_manticore::MAX_MATCHES).div_ceil(per_page);
+                template_data.pages = page_links(page, template_data.total_pages);
+                template_data.facets = facet_views(&facets, &response.aggregations, &filters, &link);
+                let manticore = &state.config.manticore;
+                template_data.results = response.hits.hits
+                    .into_iter()
+                    .map(|hit| search_manticore::search_result(hit, &manticore.title_field, &manticore.highlight_fields))
+                    .collect();
+            },
+            Err(e) => {
+                tracing::error!("Chyba při dotazu do Manticore: {}", e);
+                template_data.total_hits = -2; // <-- ZMĚNA: -2 znamená chyba
+                template_data.unavailable = Some(UnavailableTemplate {
+                    service: "Manticore Search".to_string(),
+                    detail: e.to_string(),
+                    status: None,
+                });
             }
         }
     }
 
-    // 3. Vytvoříme 'tera::Context'
+    // 6. Vytvoříme 'tera::Context'
     let context = match Context::from_serialize(&template_data) {
         Ok(ctx) => ctx,
         Err(e) => {
@@ -278,7 +1079,7 @@ pub async fn page_search(
         }
     };
 
-    // 4. Renderujeme šablonu
+    // 7. Renderujeme šablonu
     match state.tera.render("search.html", &context) {
         Ok(html) => Html(html).into_response(),
         Err(e) => {
@@ -286,4 +1087,292 @@ pub async fn page_search(
             (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response()
         }
     }
-}
\ No newline at end of file
+}
+
+/// Facety pro postranní panel. Každá hodnota má odkaz, který ji
+/// přidá jako filtr (aktivní hodnota ho naopak odebere).
+fn facet_views(
+    facets: &[&FacetConfig],
+    aggregations: &BTreeMap<String, ManticoreAggregation>,
+    filters: &[search_manticore::SearchFilter],
+    link: &SearchLink,
+) -> Vec<SearchFacet> {
+    facets
+        .iter()
+        .filter_map(|facet| {
+            let buckets = &aggregations.get(&facet.field)?.buckets;
+            // Histogram vrací všechny dny vzestupně - ukážeme posledních 'size'
+            let buckets = match facet.kind {
+                FacetKind::Date => &buckets[buckets.len().saturating_sub(facet.size as usize)..],
+                FacetKind::Terms => &buckets[..],
+            };
+            let buckets = buckets
+                .iter()
+                .filter_map(|bucket| {
+                    let filter = search_manticore::bucket_filter(facet, bucket)?;
+                    let active = filters.contains(&filter);
+                    let toggled: Vec<_> = if active {
+                        filters.iter().filter(|other| **other != filter).cloned().collect()
+                    } else {
+                        filters.iter().cloned().chain([filter.clone()]).collect()
+                    };
+                    Some(SearchFacetBucket {
+                        label: filter.label(),
+                        count: bucket.doc_count,
+                        active,
+                        href: link.href(&toggled),
+                    })
+                })
+                .collect();
+            Some(SearchFacet { field: facet.field.clone(), buckets })
+        })
+        .collect()
+}
+
+/// Čísla stránek pro odkazy: první, poslední a okolí aktuální.
+/// Vynechané úseky jsou 'None' (v šabloně "…").
+fn page_links(current: u32, total_pages: u32) -> Vec<Option<u32>> {
+    let mut links = Vec::new();
+    for page in 1..=total_pages {
+        let near = page.abs_diff(current) <= SEARCH_PAGE_WINDOW;
+        if page == 1 || page == total_pages || near {
+            links.push(Some(page));
+        } else if links.last() != Some(&None) {
+            links.push(None);
+        }
+    }
+    links
+}
+
+/// Query parametry pro /search/admin. Kromě výběru tabulky nesou
+/// i výsledek akce - POST po provedení přesměruje sem ('303').
+#[derive(Deserialize)]
+pub struct SearchAdminParams {
+    // Tabulka, jejíž schéma a stav zobrazit
+    table: Option<String>,
+    notice: Option<String>,
+    error: Option<String>,
+    // Formulář nové tabulky po neúspěšném vytvoření
+    name: Option<String>,
+    schema: Option<String>,
+}
+
+/// Formulář akce na /search/admin ('create', 'truncate', 'optimize')
+#[derive(Deserialize)]
+pub struct SearchAdminForm {
+    action: String,
+    #[serde(default)]
+    table: String,
+    // Jen pro 'create': sloupce 'název typ', jeden na řádek
+    #[serde(default)]
+    schema: String,
+}
+
+/// Handler pro stránku /search/admin - přehled tabulek v Manticore
+pub async fn page_search_admin(
+    State(state): State<AppState>,
+    Query(params): Query<SearchAdminParams>,
+) -> Response {
+    tracing::info!("Obsloužen požadavek na /search/admin (tabulka: {:?})", params.table);
+    render_search_admin(&state, params).await
+}
+
+/// Pochází požadavek z naší stránky? Formulář z cizího webu by jinak
+/// mohl v prohlížeči správce vyprázdnit tabulku (CSRF).
+///
+/// Prohlížeče posílají 'Sec-Fetch-Site', starší aspoň 'Origin'.
+/// Požadavek bez obou hlaviček (curl, skripty) se pouští.
+fn is_same_origin(headers: &HeaderMap) -> bool {
+    if let Some(site) = headers.get("sec-fetch-site") {
+        return site == "same-origin";
+    }
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let (Ok(origin), Some(Ok(host))) = (origin.to_str(), headers.get(header::HOST).map(|h| h.to_str())) else {
+        return false;
+    };
+    origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+        .is_some_and(|origin_host| origin_host == host)
+}
+
+/// Handler pro POST /search/admin - vytvoření, vyprázdnění a optimalizace tabulky.
+///
+/// Po akci přesměruje ('303') zpět na stránku, aby obnovení
+/// stránky v prohlížeči akci neopakovalo.
+pub async fn post_search_admin(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(form): Form<SearchAdminForm>,
+) -> Response {
+    if !is_same_origin(&headers) {
+        tracing::warn!("Manticore admin: odmítnuta akce '{}' z cizí stránky", form.action);
+        return (axum::http::StatusCode::FORBIDDEN, "Požadavek nepochází z tohoto dashboardu").into_response();
+    }
+
+    let table = form.table.trim().to_string();
+    tracing::info!("Manticore admin: akce '{}' pro tabulku '{}'", form.action, table);
+
+    let client = &state.manticore_client;
+    let base_url = &state.manticore_base_url;
+    let valid_table = search_manticore::validate_field(&table).is_ok();
+    let result = match form.action.as_str() {
+        _ if !valid_table => {
+            Err(format!("Neplatný název tabulky '{}' (povolené jsou písmena, číslice a '_')", table))
+        }
+        "create" => match search_manticore::parse_schema(&form.schema) {
+            Ok(columns) => search_manticore::create_table(client, base_url, &table, &columns)
+                .await
+                .map(|_| format!("Tabulka '{}' byla vytvořena.", table))
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(format!("Schéma: {}", e)),
+        },
+        "truncate" => search_manticore::truncate_table(client, base_url, &table)
+            .await
+            .map(|_| format!("Tabulka '{}' byla vyprázdněna.", table))
+            .map_err(|e| e.to_string()),
+        "optimize" => search_manticore::optimize_table(client, base_url, &table)
+            .await
+            .map(|_| format!("Optimalizace tabulky '{}' běží na pozadí.", table))
+            .map_err(|e| e.to_string()),
+        other => Err(format!("Neznámá akce '{}'", other)),
+    };
+
+    let mut url = form_urlencoded::Serializer::new(String::new());
+    match result {
+        Ok(notice) => {
+            url.append_pair("table", &table).append_pair("notice", &notice);
+        }
+        Err(e) => {
+            tracing::warn!("Manticore admin: akce '{}' pro '{}' selhala: {}", form.action, table, e);
+            if form.action == "create" {
+                // Formulář nové tabulky vrátíme vyplněný, ať jde chyba opravit
+                url.append_pair("name", &table).append_pair("schema", &form.schema);
+            } else if valid_table {
+                url.append_pair("table", &table);
+            }
+            url.append_pair("error", &e);
+        }
+    }
+    Redirect::to(&format!("/search/admin?{}", url.finish())).into_response()
+}
+
+/// Načte tabulky (a detail vybrané) a vykreslí 'search_admin.html'
+async fn render_search_admin(state: &AppState, params: SearchAdminParams) -> Response {
+    let client = &state.manticore_client;
+    let base_url = &state.manticore_base_url;
+
+    let mut template_data = SearchAdminTemplate {
+        tables: vec![],
+        selected: params.table.filter(|table| !table.is_empty()),
+        columns: vec![],
+        status: vec![],
+        column_types: search_manticore::COLUMN_TYPES.to_vec(),
+        create_name: params.name.unwrap_or_default(),
+        create_schema: params.schema.unwrap_or_default(),
+        notice: params.notice,
+        error: params.error,
+        unavailable: None,
+    };
+
+    // 1. Tabulky a jejich stav (dotazy na stav běží souběžně).
+    //    Chybu převedeme na text hned - 'Box<dyn Error>' není 'Send'.
+    let tables = search_manticore::show_tables(client, base_url)
+        .await
+        .map_err(|e| e.to_string());
+    match tables {
+        Ok(tables) => {
+            template_data.tables = futures_util::future::join_all(
+                tables
+                    .into_iter()
+                    .map(|(name, kind)| search_manticore::table_info(client, base_url, name, kind)),
+            )
+            .await;
+        }
+        Err(e) => {
+            tracing::error!("Chyba při načítání tabulek z Manticore: {}", e);
+            template_data.unavailable = Some(UnavailableTemplate {
+                service: "Manticore Search".to_string(),
+                detail: e,
+                status: None,
+            });
+        }
+    }
+
+    // 2. Schéma a celý stav vybrané tabulky
+    if template_data.unavailable.is_none() && let Some(table) = &template_data.selected {
+        let detail = async {
+            let columns = search_manticore::describe_table(client, base_url, table)
+                .await
+                .map_err(|e| e.to_string())?;
+            let status = search_manticore::table_status(client, base_url, table)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok::<_, String>((columns, status))
+        };
+        match detail.await {
+            Ok((columns, status)) => {
+                template_data.columns = columns;
+                template_data.status = status;
+            }
+            Err(e) => {
+                tracing::warn!("Detail tabulky '{}' nelze načíst: {}", table, e);
+                template_data.error.get_or_insert_with(|| format!("Tabulku '{}' nelze načíst: {}", table, e));
+                template_data.selected = None;
+            }
+        }
+    }
+
+    // 3. Vytvoříme 'tera::Context'
+    let context = match Context::from_serialize(&template_data) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            tracing::error!("Chyba při serializaci Search admin kontextu: {}", e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response();
+        }
+    };
+
+    // 4. Renderujeme šablonu
+    match state.tera.render("search_admin.html", &context) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Chyba při renderování šablony 'search_admin.html': {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response()
+        }
+    }
+}
+/// Handler pro stránku /health - přehled dostupnosti všech backendů
+pub async fn page_health(
+    State(state): State<AppState>
+) -> Response {
+    tracing::info!("Obsloužen požadavek na /health");
+
+    // 1. Spustíme sondy (každá má vlastní timeout)
+    let report = health::check_all(&state).await;
+
+    // 2. Připravíme View Model
+    let template_data = HealthTemplate {
+        app_name: state.app_name.clone(),
+        report,
+    };
+
+    // 3. Vytvoříme 'tera::Context'
+    let context = match Context::from_serialize(&template_data) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            tracing::error!("Chyba při serializaci Health kontextu: {}", e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response();
+        }
+    };
+
+    // 4. Renderujeme šablonu
+    match state.tera.render("health.html", &context) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Chyba při renderování šablony 'health.html': {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Chyba serveru").into_response()
+        }
+    }
+}
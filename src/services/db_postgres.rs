@@ -1,5 +1,7 @@
-use sqlx::PgPool;
-use crate::models::{MojeData,SenzorData}; // Náš datový struct
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use crate::config::IndexerTable;
+use crate::models::{BucketInterval,IndexerRow,MojeData,SenzorAggregate,SenzorData,TimescaleFilter}; // Náš datový struct
 
 /// Naše "fasáda" pro komunikaci s Postgres.
 ///
@@ -21,24 +23,221 @@ pub async fn get_data_from_postgres(
 
     Ok(data) // Vrátí vektor dat
 }
+/// Načte záznamy ze senzorů podle filtru (časový rozsah, senzory, limit).
+///
+/// Dotaz se skládá přes 'QueryBuilder' - všechny hodnoty z URL
+/// jsou předány jako bind parametry, nikdy se nevkládají do SQL textu.
 pub async fn get_timeseries_from_postgres(
-    pool: &PgPool
+    pool: &PgPool,
+    filter: &TimescaleFilter,
 ) -> Result<Vec<SenzorData>, sqlx::Error> {
-    
-    // Použijeme 'query_as!' pro mapování na náš nový struct
-    // Díky 'chrono' feature v sqlx si poradí s 'TIMESTAMPTZ'
-    let data = sqlx::query_as!(
-        SenzorData,
-        // SQL dotaz je prostý SELECT, TimescaleDB se postará o rychlost
+
+    let mut query = QueryBuilder::<Postgres>::new(
+        r#"SELECT "time", senzor_id, hodnota FROM senzor_data WHERE TRUE"#
+    );
+
+    if let Some(from) = filter.from {
+        query.push(r#" AND "time" >= "#).push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        query.push(r#" AND "time" <= "#).push_bind(to);
+    }
+    if !filter.senzor_ids.is_empty() {
+        query.push(" AND senzor_id = ANY(").push_bind(filter.senzor_ids.clone()).push(")");
+    }
+
+    // TimescaleDB se postará o rychlost (chunk exclusion podle "time")
+    query.push(r#" ORDER BY "time" DESC LIMIT "#).push_bind(filter.limit);
+
+    let data = query
+        .build_query_as::<SenzorData>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(data)
+}
+
+/// Načte seznam všech známých senzorů (pro výběr ve formuláři).
+pub async fn get_senzor_ids(
+    pool: &PgPool
+) -> Result<Vec<String>, sqlx::Error> {
+    let ids = sqlx::query_scalar!(
+        "SELECT DISTINCT senzor_id FROM senzor_data ORDER BY senzor_id"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(ids)
+}
+
+/// Agreguje data jednoho senzoru do časových kbelíků ('time_bucket').
+///
+/// Pro každý kbelík vrací min, max, průměr, počet a poslední hodnotu,
+/// takže graf přes týdny dat nemusí stahovat surové řádky.
+/// Používáme běžný 'query_as' (ne makro), protože 'time_bucket' a 'last'
+/// existují jen v databázi s rozšířením TimescaleDB.
+pub async fn get_aggregated_timeseries(
+    pool: &PgPool,
+    senzor_id: &str,
+    interval: BucketInterval,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<SenzorAggregate>, sqlx::Error> {
+
+    let data = sqlx::query_as::<_, SenzorAggregate>(
         r#"
-        SELECT "time", senzor_id, hodnota 
+        SELECT
+            time_bucket($1::interval, "time") AS bucket,
+            min(hodnota) AS min,
+            max(hodnota) AS max,
+            avg(hodnota) AS avg,
+            count(*) AS count,
+            last(hodnota, "time") AS last
         FROM senzor_data
-        ORDER BY "time" DESC
-        LIMIT 100
+        WHERE senzor_id = $2 AND "time" >= $3 AND "time" < $4
+        GROUP BY bucket
+        ORDER BY bucket
         "#
     )
+    .bind(interval.as_pg_interval())
+    .bind(senzor_id)
+    .bind(from)
+    .bind(to)
     .fetch_all(pool)
     .await?;
 
     Ok(data)
-}
\ No newline at end of file
+}
+
+/// Zapíše dávku řádků do 'senzor_data' jedním INSERTem.
+///
+/// Vrací počet vložených řádků. Dávka musí mít nejvýš ~20 000
+/// řádků (Postgres povolí max. 65535 bind parametrů).
+pub async fn insert_senzor_data(
+    pool: &PgPool,
+    rows: &[SenzorData],
+) -> Result<u64, sqlx::Error> {
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let mut query = QueryBuilder::<Postgres>::new(
+        r#"INSERT INTO senzor_data ("time", senzor_id, hodnota) "#
+    );
+    query.push_values(rows, |mut row, data| {
+        row.push_bind(data.time)
+            .push_bind(&data.senzor_id)
+            .push_bind(data.hodnota);
+    });
+
+    let result = query.build().execute(pool).await?;
+    Ok(result.rows_affected())
+}
+
+/// Načte sloupce tabulky a jejich typy (v pořadí z definice tabulky).
+///
+/// 'table' může být i 'schema.tabulka', jinak se hledá v 'current_schema()'.
+pub async fn get_table_columns(
+    pool: &PgPool,
+    table: &str,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let (schema, name) = match table.split_once('.') {
+        Some((schema, name)) => (Some(schema), name),
+        None => (None, table),
+    };
+
+    let columns = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT column_name::text, data_type::text
+        FROM information_schema.columns
+        WHERE table_schema = COALESCE($1, current_schema()) AND table_name = $2
+        ORDER BY ordinal_position
+        "#
+    )
+    .bind(schema)
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(columns)
+}
+
+/// Načte další dávku řádků pro indexer (stránkování přes klíč, ne OFFSET).
+///
+/// Bez 'watermark_column' bere řádky s ID větším než 'after_id',
+/// s ním řádky za dvojicí ('after_watermark', 'after_id').
+/// 'columns' jsou sloupce dokumentu a jejich typy z 'get_table_columns'
+/// - časy se převedou na unix timestamp, jak je Manticore ukládá.
+///
+/// Řádky s NULL vodoznakem se nevrátí nikdy (porovnání s NULL neplatí),
+/// indexer je tedy přeskakuje - 'watermark_column' má být NOT NULL.
+///
+/// Názvy tabulky a sloupců se vkládají do SQL jako identifikátory,
+/// musí být zvalidované ('[A-Za-z0-9_]').
+pub async fn fetch_index_batch(
+    pool: &PgPool,
+    table: &IndexerTable,
+    columns: &[(String, String)],
+    after_id: Option<i64>,
+    after_watermark: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<IndexerRow>, sqlx::Error> {
+    let id = format!(r#"t."{}""#, table.id_column);
+    let watermark = table
+        .watermark_column
+        .as_ref()
+        .map(|column| format!(r#"t."{}"::timestamptz"#, column));
+    let source = table
+        .table
+        .split('.')
+        .map(|part| format!(r#""{}""#, part))
+        .collect::<Vec<_>>()
+        .join(".");
+    let doc_columns = columns
+        .iter()
+        .map(|(name, data_type)| match data_type.as_str() {
+            "date" | "timestamp without time zone" | "timestamp with time zone" => {
+                format!(r#"extract(epoch FROM t."{0}")::bigint AS "{0}""#, name)
+            }
+            _ => format!(r#"t."{0}" AS "{0}""#, name),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut query = QueryBuilder::<Postgres>::new(format!(
+        "SELECT {}::bigint AS doc_id, {} AS watermark, to_jsonb(d)::text AS doc FROM {} t, LATERAL (SELECT {}) d WHERE ",
+        id,
+        watermark.as_deref().unwrap_or("NULL::timestamptz"),
+        source,
+        doc_columns,
+    ));
+
+    match (&watermark, after_watermark) {
+        (Some(watermark), Some(after)) => {
+            query.push(format!("({}, {}::bigint) > (", watermark, id))
+                .push_bind(after)
+                .push(", ")
+                .push_bind(after_id.unwrap_or(i64::MIN))
+                .push(")");
+        }
+        (Some(watermark), None) => {
+            query.push(format!("{} IS NOT NULL", watermark));
+        }
+        (None, _) => {
+            query.push(format!("{}::bigint > ", id)).push_bind(after_id.unwrap_or(i64::MIN));
+        }
+    }
+
+    match &watermark {
+        Some(watermark) => query.push(format!(" ORDER BY {}, {}", watermark, id)),
+        None => query.push(format!(" ORDER BY {}", id)),
+    };
+    query.push(" LIMIT ").push_bind(limit);
+
+    let rows = query
+        .build_query_as::<IndexerRow>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows)
+}
@@ -1,25 +1,448 @@
-use redis::{Client as ValkeyClient,RedisResult};
+use chrono::DateTime;
+use crate::models::{
+    ValkeyClientEntry, ValkeyClientsInfo, ValkeyHashField, ValkeyKeyInfo, ValkeyKeyspaceDb,
+    ValkeyListItem, ValkeyMemoryInfo, ValkeyPage, ValkeyPersistenceInfo, ValkeyReplicationInfo,
+    ValkeyServerInfo, ValkeySlowlogEntry, ValkeyStatsInfo, ValkeyStreamEntry, ValkeyValue,
+    ValkeyZsetMember,
+};
+use std::collections::HashMap;
+use redis::aio::MultiplexedConnection;
+use redis::{Client as ValkeyClient,RedisResult,Value};
 
 
-/// Načte hodnotu pro daný klíč z Valkey.
+/// Projde jednu "stránku" klíčů pomocí 'SCAN' (nikdy 'KEYS *').
 ///
-/// Vrací 'RedisResult<Option<String>>', protože klíč
-/// nemusí existovat (GET vrátí 'nil', což 'redis-rs'
-/// správně přeloží na 'Ok(None)', pokud cílový typ je 'Option').
-pub async fn get_valkey_kv(
+/// 'cursor' 0 znamená začátek. Vrací další kurzor (0 = konec
+/// průchodu) a informace o nalezených klíčích. 'count' je pro Valkey
+/// jen nápověda - stránka může vrátit méně klíčů, i žádný.
+pub async fn scan_keys(
     client: &ValkeyClient,
-    key: &str,
-) -> RedisResult<Option<String>> {
-    
-    // Získáme multiplexované připojení
+    cursor: u64,
+    pattern: &str,
+    count: usize,
+) -> RedisResult<(u64, Vec<ValkeyKeyInfo>)> {
     let mut con = client.get_multiplexed_async_connection().await?;
 
-    // Spustíme příkaz GET a explicitně řekneme, že
-    // očekáváme 'Option<String>'.
-    let value: Option<String> = redis::cmd("GET")
-        .arg(key) // Přidáme argument (náš klíč)
+    // Klíče jsou binární - jeden ne-UTF-8 klíč nesmí shodit celou stránku
+    let (next_cursor, mut keys): (u64, Vec<Vec<u8>>) = redis::cmd("SCAN")
+        .arg(cursor)
+        .arg("MATCH")
+        .arg(pattern)
+        .arg("COUNT")
+        .arg(count)
         .query_async(&mut con)
         .await?;
-    
+    keys.sort();
+
+    let infos = get_keys_info(&mut con, &keys).await?;
+    Ok((next_cursor, infos))
+}
+
+/// Načte informace o jednom klíči. Vrací 'None', pokud klíč neexistuje.
+pub async fn get_key_info(
+    client: &ValkeyClient,
+    key: &[u8],
+) -> RedisResult<Option<ValkeyKeyInfo>> {
+    let mut con = client.get_multiplexed_async_connection().await?;
+    let info = get_keys_info(&mut con, &[key.to_vec()]).await?.pop();
+    Ok(info.filter(|info| info.key_type != "none"))
+}
+
+/// Zjistí TYPE, TTL a MEMORY USAGE pro všechny klíče jedním pipeline
+/// (jeden round-trip na síti místo tří na každý klíč).
+///
+/// Příkazy dostanou klíč tak, jak je; pro zobrazení se převede
+/// na text (neplatné UTF-8 nahradí '�') a pro odkaz na detail
+/// se u takového klíče přidá jeho hex podoba ('key_raw').
+async fn get_keys_info(
+    con: &mut MultiplexedConnection,
+    keys: &[Vec<u8>],
+) -> RedisResult<Vec<ValkeyKeyInfo>> {
+    if keys.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.cmd("TYPE").arg(key)
+            .cmd("TTL").arg(key)
+            .cmd("MEMORY").arg("USAGE").arg(key);
+    }
+    let values: Vec<Value> = pipe.query_async(con).await?;
+
+    let infos = keys
+        .iter()
+        .zip(values.chunks(3))
+        .map(|(key, chunk)| ValkeyKeyInfo {
+            key: lossy(key.clone()),
+            key_raw: raw_key_param(key),
+            key_type: redis::from_redis_value(&chunk[0]).unwrap_or_else(|_| "none".to_string()),
+            ttl: redis::from_redis_value(&chunk[1]).unwrap_or(-2),
+            memory_bytes: redis::from_redis_value(&chunk[2]).unwrap_or(None),
+        })
+        .collect();
+
+    Ok(infos)
+}
+
+/// Načte hodnotu klíče podle jeho typu (viz 'get_key_info').
+///
+/// Velké kolekce se čtou po stránkách - HSCAN/SSCAN s kurzorem,
+/// LRANGE/ZRANGE s offsetem a XRANGE od posledního ID.
+/// Binární obsah se převádí na text "lossy" (neplatné UTF-8 -> '�').
+pub async fn get_key_value(
+    client: &ValkeyClient,
+    key: &[u8],
+    key_type: &str,
+    page: &ValkeyPage,
+) -> RedisResult<ValkeyValue> {
+    let mut con = client.get_multiplexed_async_connection().await?;
+    let count = page.count as i64;
+
+    let value = match key_type {
+        "string" => {
+            let value: Option<Vec<u8>> = redis::cmd("GET").arg(key).query_async(&mut con).await?;
+            ValkeyValue::String { value: value.map(lossy) }
+        }
+        "hash" => {
+            let total: i64 = redis::cmd("HLEN").arg(key).query_async(&mut con).await?;
+            // Malý hash načteme najednou, velký procházíme kurzorem
+            let (next_cursor, flat): (u64, Vec<Vec<u8>>) = if page.cursor == 0 && total <= count {
+                (0, redis::cmd("HGETALL").arg(key).query_async(&mut con).await?)
+            } else {
+                redis::cmd("HSCAN")
+                    .arg(key)
+                    .arg(page.cursor)
+                    .arg("COUNT")
+                    .arg(page.count)
+                    .query_async(&mut con)
+                    .await?
+            };
+            ValkeyValue::Hash { total, fields: pairs(flat), next_cursor }
+        }
+        "list" => {
+            let total: i64 = redis::cmd("LLEN").arg(key).query_async(&mut con).await?;
+            let values: Vec<Vec<u8>> = redis::cmd("LRANGE")
+                .arg(key)
+                .arg(page.offset)
+                .arg(page.offset + count - 1)
+                .query_async(&mut con)
+                .await?;
+            let items = values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| ValkeyListItem { index: page.offset + i as i64, value: lossy(value) })
+                .collect();
+            let next_offset = Some(page.offset + count).filter(|next| *next < total);
+            ValkeyValue::List { total, items, next_offset }
+        }
+        "set" => {
+            let total: i64 = redis::cmd("SCARD").arg(key).query_async(&mut con).await?;
+            let (next_cursor, members): (u64, Vec<Vec<u8>>) = redis::cmd("SSCAN")
+                .arg(key)
+                .arg(page.cursor)
+                .arg("COUNT")
+                .arg(page.count)
+                .query_async(&mut con)
+                .await?;
+            let mut members: Vec<String> = members.into_iter().map(lossy).collect();
+            members.sort();
+            ValkeyValue::Set { total, members, next_cursor }
+        }
+        "zset" => {
+            let total: i64 = redis::cmd("ZCARD").arg(key).query_async(&mut con).await?;
+            let flat: Vec<Vec<u8>> = redis::cmd("ZRANGE")
+                .arg(key)
+                .arg(page.offset)
+                .arg(page.offset + count - 1)
+                .arg("WITHSCORES")
+                .query_async(&mut con)
+                .await?;
+            let members = pairs(flat)
+                .into_iter()
+                .map(|pair| ValkeyZsetMember {
+                    member: pair.field,
+                    score: pair.value.parse().unwrap_or(f64::NAN),
+                })
+                .collect();
+            let next_offset = Some(page.offset + count).filter(|next| *next < total);
+            ValkeyValue::Zset { total, members, next_offset }
+        }
+        "stream" => {
+            let total: i64 = redis::cmd("XLEN").arg(key).query_async(&mut con).await?;
+            // Odpověď je [[id, [pole, hodnota...]], ...] - redis-rs ji neumí
+            // spolehlivě převést na vektor dvojic, proto ji rozebereme ručně
+            let raw: Vec<Value> = redis::cmd("XRANGE")
+                .arg(key)
+                .arg(page.from_id.as_deref().unwrap_or("-"))
+                .arg("+")
+                .arg("COUNT")
+                .arg(page.count)
+                .query_async(&mut con)
+                .await?;
+            let entries: Vec<ValkeyStreamEntry> = raw
+                .iter()
+                .map(|entry| {
+                    let (id, flat): (String, Vec<Vec<u8>>) = redis::from_redis_value(entry)?;
+                    Ok(ValkeyStreamEntry { id, fields: pairs(flat) })
+                })
+                .collect::<RedisResult<_>>()?;
+            // Plná stránka = možná existují další záznamy
+            let next_from_id = entries
+                .last()
+                .filter(|_| entries.len() == page.count)
+                .map(|entry| format!("({}", entry.id));
+            ValkeyValue::Stream { total, entries, next_from_id }
+        }
+        other => ValkeyValue::Unsupported { key_type: other.to_string() },
+    };
+
     Ok(value)
-}
\ No newline at end of file
+}
+
+/// Převede plochý seznam [pole, hodnota, pole, hodnota...] na dvojice
+fn pairs(flat: Vec<Vec<u8>>) -> Vec<ValkeyHashField> {
+    let mut iter = flat.into_iter();
+    let mut result = Vec::new();
+    while let (Some(field), Some(value)) = (iter.next(), iter.next()) {
+        result.push(ValkeyHashField { field: lossy(field), value: lossy(value) });
+    }
+    result
+}
+
+/// Hex podoba klíče pro URL parametr 'key_raw'.
+///
+/// Jen pro klíče, které nejsou platné UTF-8 - ostatní se předávají
+/// čitelně v parametru 'key'.
+pub fn raw_key_param(key: &[u8]) -> Option<String> {
+    if std::str::from_utf8(key).is_ok() {
+        return None;
+    }
+    Some(key.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Opak 'raw_key_param' - 'None' pro neplatný hex řetězec
+pub fn parse_raw_key(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Bajty na text - neplatné UTF-8 sekvence nahradí znakem '�'
+fn lossy(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes)
+        .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+/// Načte a naparsuje výstup 'INFO' do typovaných struktur.
+pub async fn get_server_info(
+    client: &ValkeyClient,
+) -> RedisResult<ValkeyServerInfo> {
+    let mut con = client.get_multiplexed_async_connection().await?;
+    let raw: String = redis::cmd("INFO").arg("everything").query_async(&mut con).await?;
+    Ok(parse_info(&raw))
+}
+
+/// Načte posledních 'count' pomalých příkazů ('SLOWLOG GET').
+pub async fn get_slowlog(
+    client: &ValkeyClient,
+    count: usize,
+) -> RedisResult<Vec<ValkeySlowlogEntry>> {
+    let mut con = client.get_multiplexed_async_connection().await?;
+    let raw: Vec<Value> = redis::cmd("SLOWLOG")
+        .arg("GET")
+        .arg(count)
+        .query_async(&mut con)
+        .await?;
+
+    // Záznam je [id, čas, trvání_us, [argumenty], adresa, jméno]
+    // (adresa a jméno klienta jsou až od Redis 4.0)
+    let entries = raw
+        .iter()
+        .filter_map(|entry| match entry {
+            Value::Bulk(fields) if fields.len() >= 4 => Some(fields),
+            _ => None,
+        })
+        .map(|fields| {
+            let args: Vec<Vec<u8>> = redis::from_redis_value(&fields[3]).unwrap_or_default();
+            let timestamp: i64 = redis::from_redis_value(&fields[1]).unwrap_or(0);
+            ValkeySlowlogEntry {
+                id: redis::from_redis_value(&fields[0]).unwrap_or(0),
+                timestamp: DateTime::from_timestamp(timestamp, 0),
+                duration_us: redis::from_redis_value(&fields[2]).unwrap_or(0),
+                command: args.into_iter().map(lossy).collect::<Vec<_>>().join(" "),
+                client_addr: fields.get(4).and_then(|v| redis::from_redis_value(v).ok()).unwrap_or_default(),
+                client_name: fields.get(5).and_then(|v| redis::from_redis_value(v).ok()).unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+/// Načte seznam připojených klientů ('CLIENT LIST').
+pub async fn get_client_list(
+    client: &ValkeyClient,
+) -> RedisResult<Vec<ValkeyClientEntry>> {
+    let mut con = client.get_multiplexed_async_connection().await?;
+    let raw: String = redis::cmd("CLIENT").arg("LIST").query_async(&mut con).await?;
+
+    // Každý řádek je 'id=3 addr=127.0.0.1:5000 name= age=10 ...'
+    let clients = raw
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields: HashMap<&str, &str> = line
+                .split_whitespace()
+                .filter_map(|pair| pair.split_once('='))
+                .collect();
+            let text = |name: &str| fields.get(name).copied().unwrap_or_default().to_string();
+            let number = |name: &str| fields.get(name).and_then(|v| v.parse().ok()).unwrap_or(0);
+            ValkeyClientEntry {
+                id: text("id"),
+                addr: text("addr"),
+                name: text("name"),
+                age_seconds: number("age"),
+                idle_seconds: number("idle"),
+                db: text("db"),
+                flags: text("flags"),
+                cmd: text("cmd"),
+            }
+        })
+        .collect();
+
+    Ok(clients)
+}
+
+/// Naparsuje textový výstup 'INFO' ('# Sekce' a řádky 'klíč:hodnota').
+///
+/// Názvy klíčů jsou napříč sekcemi unikátní, stačí nám tedy jedna
+/// mapa. Chybějící nebo neplatné hodnoty se nahradí výchozími.
+fn parse_info(raw: &str) -> ValkeyServerInfo {
+    let fields: HashMap<&str, &str> = raw
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .collect();
+
+    let text = |name: &str| fields.get(name).copied().unwrap_or_default().to_string();
+    let number = |name: &str| -> u64 { fields.get(name).and_then(|v| v.parse().ok()).unwrap_or(0) };
+    let flag = |name: &str| number(name) != 0;
+
+    let used_memory = number("used_memory");
+    let maxmemory = number("maxmemory");
+    let keyspace_hits = number("keyspace_hits");
+    let keyspace_misses = number("keyspace_misses");
+
+    // 'db0:keys=10,expires=2,avg_ttl=0'
+    let mut keyspace = fields
+        .iter()
+        .filter(|(name, _)| name.starts_with("db") && name[2..].chars().all(|c| c.is_ascii_digit()))
+        .map(|(name, value)| {
+            let stats: HashMap<&str, u64> = value
+                .split(',')
+                .filter_map(|pair| pair.split_once('='))
+                .filter_map(|(k, v)| v.parse().ok().map(|v| (k, v)))
+                .collect();
+            ValkeyKeyspaceDb {
+                db: name.to_string(),
+                keys: stats.get("keys").copied().unwrap_or(0),
+                expires: stats.get("expires").copied().unwrap_or(0),
+                avg_ttl: stats.get("avg_ttl").copied().unwrap_or(0),
+            }
+        })
+        .collect::<Vec<_>>();
+    keyspace.sort_by(|a, b| a.db.cmp(&b.db));
+
+    ValkeyServerInfo {
+        // Valkey hlásí vlastní verzi, ale kvůli kompatibilitě i 'redis_version'
+        version: fields
+            .get("valkey_version")
+            .or_else(|| fields.get("redis_version"))
+            .copied()
+            .unwrap_or_default()
+            .to_string(),
+        uptime_seconds: number("uptime_in_seconds"),
+        memory: ValkeyMemoryInfo {
+            used_memory,
+            used_memory_human: text("used_memory_human"),
+            used_memory_rss: number("used_memory_rss"),
+            used_memory_peak: number("used_memory_peak"),
+            maxmemory,
+            maxmemory_policy: text("maxmemory_policy"),
+            mem_fragmentation_ratio: fields
+                .get("mem_fragmentation_ratio")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.0),
+            usage_percent: (maxmemory > 0).then(|| used_memory as f64 / maxmemory as f64 * 100.0),
+        },
+        clients: ValkeyClientsInfo {
+            connected_clients: number("connected_clients"),
+            blocked_clients: number("blocked_clients"),
+            maxclients: number("maxclients"),
+        },
+        persistence: ValkeyPersistenceInfo {
+            loading: flag("loading"),
+            rdb_changes_since_last_save: number("rdb_changes_since_last_save"),
+            rdb_bgsave_in_progress: flag("rdb_bgsave_in_progress"),
+            rdb_last_save_time: DateTime::from_timestamp(number("rdb_last_save_time") as i64, 0)
+                .filter(|_| number("rdb_last_save_time") > 0),
+            rdb_last_bgsave_status: text("rdb_last_bgsave_status"),
+            aof_enabled: flag("aof_enabled"),
+            aof_rewrite_in_progress: flag("aof_rewrite_in_progress"),
+            aof_last_bgrewrite_status: text("aof_last_bgrewrite_status"),
+            aof_last_write_status: text("aof_last_write_status"),
+        },
+        replication: ValkeyReplicationInfo {
+            role: text("role"),
+            connected_slaves: number("connected_slaves"),
+            master_host: fields.get("master_host").map(|v| v.to_string()),
+            master_link_status: fields.get("master_link_status").map(|v| v.to_string()),
+        },
+        stats: ValkeyStatsInfo {
+            total_commands_processed: number("total_commands_processed"),
+            instantaneous_ops_per_sec: number("instantaneous_ops_per_sec"),
+            keyspace_hits,
+            keyspace_misses,
+            hit_ratio_percent: (keyspace_hits + keyspace_misses > 0)
+                .then(|| keyspace_hits as f64 / (keyspace_hits + keyspace_misses) as f64 * 100.0),
+            expired_keys: number("expired_keys"),
+            evicted_keys: number("evicted_keys"),
+        },
+        keyspace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf8_key_has_no_raw_param() {
+        assert_eq!(raw_key_param(b"sensor:1"), None);
+        assert_eq!(raw_key_param("teplota:kuchyň".as_bytes()), None);
+    }
+
+    #[test]
+    fn non_utf8_key_round_trips_through_hex() {
+        let key = b"bin:\xff\x00";
+        let raw = raw_key_param(key).unwrap();
+        assert_eq!(raw, "62696e3aff00");
+        assert_eq!(parse_raw_key(&raw).unwrap(), key);
+        assert_eq!(parse_raw_key("62696E3AFF00").unwrap(), key);
+    }
+
+    #[test]
+    fn invalid_hex_is_rejected() {
+        assert_eq!(parse_raw_key("abc"), None);
+        assert_eq!(parse_raw_key("zz"), None);
+        assert_eq!(parse_raw_key("+f"), None);
+        assert_eq!(parse_raw_key("čč"), None);
+        assert_eq!(parse_raw_key(""), Some(vec![]));
+    }
+}
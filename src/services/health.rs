@@ -0,0 +1,134 @@
+use crate::models::{DependencyHealth, HealthReport, HealthStatus};
+use crate::state::AppState;
+use chrono::Utc;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Maximální doba, po kterou čekáme na odpověď jednoho backendu.
+/// Kubernetes sonda má vlastní timeout, takže musíme odpovědět dřív.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pokud MQTT event loop nevydal žádnou událost déle než tuto dobu,
+/// považujeme sběrač za mrtvý. Keep-alive je 5s, takže PINGRESP
+/// by měl chodit mnohem častěji.
+pub const MQTT_STALE_AFTER: Duration = Duration::from_secs(30);
+
+/// Spustí jednu sondu s timeoutem a změří její latenci.
+///
+/// Sonda vrací 'Ok(detail)' při úspěchu a 'Err(popis)' při chybě.
+async fn run_probe<F>(name: &str, probe: F) -> DependencyHealth
+where
+    F: Future<Output = Result<String, String>>,
+{
+    let started = Instant::now();
+    let result = tokio::time::timeout(PROBE_TIMEOUT, probe).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let (status, detail) = match result {
+        Ok(Ok(detail)) => (HealthStatus::Up, detail),
+        Ok(Err(e)) => (HealthStatus::Down, e),
+        Err(_) => (
+            HealthStatus::Down,
+            format!("Timeout po {} ms", PROBE_TIMEOUT.as_millis()),
+        ),
+    };
+
+    if status == HealthStatus::Down {
+        tracing::warn!("Health: {} je nedostupný: {}", name, detail);
+    }
+
+    DependencyHealth {
+        name: name.to_string(),
+        status,
+        latency_ms,
+        detail,
+    }
+}
+
+/// Postgres: jednoduchý 'SELECT 1' přes sdílený pool.
+async fn probe_postgres(state: &AppState) -> Result<String, String> {
+    let pool = state.postgres_pool.get().await.map_err(|s| s.summary())?;
+    sqlx::query("SELECT 1")
+        .execute(&pool)
+        .await
+        .map(|_| "SELECT 1 OK".to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Valkey: 'PING' přes multiplexované připojení.
+async fn probe_valkey(state: &AppState) -> Result<String, String> {
+    let client = state.valkey_client.get().await.map_err(|s| s.summary())?;
+    let mut con = client
+        .get_multiplexed_async_connection()
+        .await
+        .map_err(|e| e.to_string())?;
+    let pong: String = redis::cmd("PING")
+        .query_async(&mut con)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(pong)
+}
+
+/// Manticore: stačí nám, že HTTP API odpoví úspěšným statusem.
+async fn probe_manticore(state: &AppState) -> Result<String, String> {
+    let response = state
+        .manticore_client
+        .get(&state.manticore_base_url)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(format!("HTTP {}", status))
+    } else {
+        Err(format!("HTTP {}", status))
+    }
+}
+
+/// MQTT: sběrač na pozadí zapisuje stav spojení a čas poslední události
+/// z event loopu. Nic se neposílá po síti, jen kontrolujeme, jestli
+/// je sběrač připojený a smyčka žije.
+pub async fn probe_mqtt(state: &AppState) -> Result<String, String> {
+    let connection = state.mqtt_connection.read().await.status.clone();
+    if !connection.connected {
+        return Err(connection.summary());
+    }
+
+    let last_event = *state.mqtt_last_event.read().await;
+    match last_event {
+        None => Err("Event loop zatím nevydal žádnou událost".to_string()),
+        Some(at) => {
+            let age = (Utc::now() - at).to_std().unwrap_or_default();
+            if age > MQTT_STALE_AFTER {
+                Err(format!("Poslední událost před {} s", age.as_secs()))
+            } else {
+                Ok(format!("Poslední událost před {} s", age.as_secs()))
+            }
+        }
+    }
+}
+
+/// Otestuje všechny backendy z 'AppState' souběžně a vrátí souhrnný report.
+///
+/// Celkový stav je 'Up' jen tehdy, když jsou dostupné všechny závislosti.
+pub async fn check_all(state: &AppState) -> HealthReport {
+    let (postgres, valkey, manticore, mqtt) = tokio::join!(
+        run_probe("postgres", probe_postgres(state)),
+        run_probe("valkey", probe_valkey(state)),
+        run_probe("manticore", probe_manticore(state)),
+        run_probe("mqtt", probe_mqtt(state)),
+    );
+
+    let dependencies = vec![postgres, valkey, manticore, mqtt];
+    let status = if dependencies.iter().all(|d| d.status == HealthStatus::Up) {
+        HealthStatus::Up
+    } else {
+        HealthStatus::Down
+    };
+
+    HealthReport {
+        status,
+        checked_at: Utc::now(),
+        dependencies,
+    }
+}
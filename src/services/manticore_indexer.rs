@@ -0,0 +1,232 @@
+use crate::config::{IndexerConfig, IndexerTable};
+use crate::models::{IndexerStats, IndexerTableStatus};
+use crate::services::{db_postgres, search_manticore};
+use crate::state::Backend;
+use chrono::{DateTime, Utc};
+use redis::Client as ValkeyClient;
+use reqwest::Client as ManticoreClient;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Postup jedné tabulky uložený ve Valkey (hash '<prefix><tabulka>:<index>')
+#[derive(Debug, Default)]
+struct Progress {
+    last_id: Option<i64>,
+    watermark: Option<DateTime<Utc>>,
+    indexed: u64,
+}
+
+/// Klíč ve Valkey, pod kterým je postup tabulky
+fn progress_key(config: &IndexerConfig, table: &IndexerTable) -> String {
+    format!("{}{}:{}", config.progress_key_prefix, table.table, table.index)
+}
+
+/// Spustí indexer Postgres -> Manticore jako úlohu na pozadí.
+///
+/// Každý průchod vezme tabulky z konfigurace jednu po druhé a po
+/// dávkách ('batch_size') přenese řádky za uloženým vodoznakem do
+/// RT indexu ('/bulk' replace). Po každé dávce uloží postup do Valkey,
+/// takže po restartu (nebo chybě) pokračuje, kde skončil. Když jsou
+/// všechny tabulky zaindexované, čeká 'interval_secs' na další průchod.
+///
+/// Bez Valkey se neindexuje - bez uloženého postupu by se po každém
+/// restartu indexovalo znovu od začátku.
+pub fn launch_indexer(
+    config: IndexerConfig,
+    client: ManticoreClient,
+    base_url: String,
+    pool: Backend<PgPool>,
+    valkey: Backend<ValkeyClient>,
+    stats: Arc<RwLock<IndexerStats>>,
+) {
+    tokio::spawn(async move {
+        stats.write().await.tables = config
+            .tables
+            .iter()
+            .map(|table| IndexerTableStatus {
+                table: table.table.clone(),
+                index: table.index.clone(),
+                progress_key: progress_key(&config, table),
+                last_id: None,
+                watermark: None,
+                indexed: 0,
+                caught_up: false,
+                last_run_at: None,
+                last_error: None,
+            })
+            .collect();
+
+        let interval = Duration::from_secs(config.interval_secs);
+        loop {
+            for (position, table) in config.tables.iter().enumerate() {
+                let result = index_table(&config, table, position, &client, &base_url, &pool, &valkey, &stats).await;
+
+                let mut stats = stats.write().await;
+                let status = &mut stats.tables[position];
+                status.last_run_at = Some(Utc::now());
+                match result {
+                    Ok(()) => {
+                        status.caught_up = true;
+                        status.last_error = None;
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "Indexer: '{}' -> '{}' selhal: {} (další pokus za {} s)",
+                            table.table, table.index, e, interval.as_secs()
+                        );
+                        status.caught_up = false;
+                        status.last_error = Some(e);
+                    }
+                }
+            }
+            stats.write().await.last_pass_at = Some(Utc::now());
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+/// Zaindexuje jednu tabulku až na konec (dokud dávka není neúplná)
+#[allow(clippy::too_many_arguments)]
+async fn index_table(
+    config: &IndexerConfig,
+    table: &IndexerTable,
+    position: usize,
+    client: &ManticoreClient,
+    base_url: &str,
+    pool: &Backend<PgPool>,
+    valkey: &Backend<ValkeyClient>,
+    stats: &RwLock<IndexerStats>,
+) -> Result<(), String> {
+    let pool = pool.get().await.map_err(|status| format!("Postgres: {}", status.summary()))?;
+    let valkey = valkey.get().await.map_err(|status| format!("Valkey: {}", status.summary()))?;
+    let key = progress_key(config, table);
+
+    let mut progress = load_progress(&valkey, &key)
+        .await
+        .map_err(|e| format!("Valkey: {}", e))?;
+    update_status(stats, position, &progress).await;
+
+    let columns = document_columns(&pool, table).await?;
+    let mut written = 0;
+    loop {
+        let rows = db_postgres::fetch_index_batch(
+            &pool,
+            table,
+            &columns,
+            progress.last_id,
+            progress.watermark,
+            config.batch_size as i64,
+        )
+        .await
+        .map_err(|e| format!("Postgres: {}", e))?;
+
+        let docs = rows
+            .iter()
+            .map(|row| {
+                serde_json::from_str::<Value>(&row.doc)
+                    .map(|doc| (row.doc_id, doc))
+                    .map_err(|e| format!("řádek {}: {}", row.doc_id, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let count = search_manticore::bulk_replace(client, base_url, &table.index, &docs)
+            .await
+            .map_err(|e| format!("Manticore: {}", e))?;
+
+        if let Some(last) = rows.last() {
+            progress.last_id = Some(last.doc_id);
+            progress.watermark = last.watermark;
+            progress.indexed += count as u64;
+            written += count;
+            save_progress(&valkey, &key, &progress)
+                .await
+                .map_err(|e| format!("Valkey: {}", e))?;
+            update_status(stats, position, &progress).await;
+        }
+
+        if rows.len() < config.batch_size {
+            break;
+        }
+    }
+
+    if written > 0 {
+        tracing::info!(
+            "Indexer: '{}' -> '{}': zapsáno {} dokumentů (celkem {}).",
+            table.table, table.index, written, progress.indexed
+        );
+    }
+    Ok(())
+}
+
+/// Sloupce dokumentu a jejich typy: podle 'columns' z konfigurace,
+/// nebo všechny sloupce tabulky kromě 'id_column'
+async fn document_columns(pool: &PgPool, table: &IndexerTable) -> Result<Vec<(String, String)>, String> {
+    let all = db_postgres::get_table_columns(pool, &table.table)
+        .await
+        .map_err(|e| format!("Postgres: {}", e))?;
+    if all.is_empty() {
+        return Err(format!("tabulka '{}' neexistuje", table.table));
+    }
+    for required in std::iter::once(&table.id_column).chain(table.watermark_column.as_ref()) {
+        if !all.iter().any(|(name, _)| name == required) {
+            return Err(format!("tabulka '{}' nemá sloupec '{}'", table.table, required));
+        }
+    }
+
+    if table.columns.is_empty() {
+        return Ok(all.into_iter().filter(|(name, _)| *name != table.id_column).collect());
+    }
+    table
+        .columns
+        .iter()
+        .map(|column| {
+            all.iter()
+                .find(|(name, _)| name == column)
+                .cloned()
+                .ok_or_else(|| format!("tabulka '{}' nemá sloupec '{}'", table.table, column))
+        })
+        .collect()
+}
+
+async fn update_status(stats: &RwLock<IndexerStats>, position: usize, progress: &Progress) {
+    let mut stats = stats.write().await;
+    let status = &mut stats.tables[position];
+    status.last_id = progress.last_id;
+    status.watermark = progress.watermark;
+    status.indexed = progress.indexed;
+}
+
+/// Načte postup z Valkey. Chybějící klíč = začínáme od začátku.
+async fn load_progress(client: &ValkeyClient, key: &str) -> redis::RedisResult<Progress> {
+    let mut con = client.get_multiplexed_async_connection().await?;
+    let fields: HashMap<String, String> = redis::cmd("HGETALL").arg(key).query_async(&mut con).await?;
+    Ok(Progress {
+        last_id: fields.get("last_id").and_then(|v| v.parse().ok()),
+        watermark: fields
+            .get("watermark")
+            .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+        indexed: fields.get("indexed").and_then(|v| v.parse().ok()).unwrap_or(0),
+    })
+}
+
+async fn save_progress(client: &ValkeyClient, key: &str, progress: &Progress) -> redis::RedisResult<()> {
+    let mut con = client.get_multiplexed_async_connection().await?;
+    let mut cmd = redis::cmd("HSET");
+    cmd.arg(key)
+        .arg("indexed")
+        .arg(progress.indexed)
+        .arg("updated_at")
+        .arg(Utc::now().to_rfc3339());
+    if let Some(last_id) = progress.last_id {
+        cmd.arg("last_id").arg(last_id);
+    }
+    if let Some(watermark) = progress.watermark {
+        cmd.arg("watermark").arg(watermark.to_rfc3339());
+    }
+    cmd.query_async::<_, ()>(&mut con).await
+}
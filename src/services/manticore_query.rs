@@ -0,0 +1,308 @@
+use serde_json::{json, Map, Value};
+
+/// Pseudo-pole, které v Manticore znamená "všechna fulltextová pole"
+const ALL_FIELDS: &str = "_all";
+
+/// Typovaný dotaz pro Manticore JSON API (klíč 'query' v '/search').
+///
+/// Sestavuje se z konstruktorů ('Query::matches', 'BoolQuery::new()...')
+/// a na JSON se převede přes 'to_json' - bez spojení se serverem.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    // Slova v polích; prázdné 'fields' = všechna fulltextová pole
+    Match {
+        fields: Vec<String>,
+        text: String,
+        operator: Operator,
+    },
+    // Slova přesně v tomto pořadí
+    MatchPhrase { fields: Vec<String>, phrase: String },
+    // Fulltextový dotaz v syntaxi Manticore ('@title senzor -chyba'); v JSON "query_string"
+    Expression(String),
+    // Atribut má přesně tuto hodnotu
+    Equals { field: String, value: Value },
+    Range(RangeQuery),
+    GeoDistance(GeoDistance),
+    Bool(BoolQuery),
+}
+
+/// Jak spojit slova v 'match'
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Operator {
+    // Stačí jedno ze slov (výchozí v Manticore)
+    #[default]
+    Or,
+    // Musí být všechna slova
+    And,
+}
+
+/// Interval hodnot atributu, chybějící meze neomezují
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RangeQuery {
+    pub field: String,
+    pub gte: Option<Value>,
+    pub lte: Option<Value>,
+    pub lt: Option<Value>,
+}
+
+/// Dokumenty do vzdálenosti 'distance_m' metrů od bodu.
+/// Souřadnice (i v atributech) jsou ve stupních.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoDistance {
+    pub lat_field: String,
+    pub lon_field: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub distance_m: f64,
+}
+
+/// Logická kombinace dotazů
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BoolQuery {
+    pub must: Vec<Query>,
+    pub should: Vec<Query>,
+    pub must_not: Vec<Query>,
+}
+
+impl Query {
+    /// 'match' na zadaná pole (prázdné = všechna)
+    pub fn matches(fields: &[String], text: impl Into<String>, operator: Operator) -> Self {
+        Self::Match { fields: fields.to_vec(), text: text.into(), operator }
+    }
+
+    pub fn phrase(fields: &[String], phrase: impl Into<String>) -> Self {
+        Self::MatchPhrase { fields: fields.to_vec(), phrase: phrase.into() }
+    }
+
+    pub fn query_string(query: impl Into<String>) -> Self {
+        Self::Expression(query.into())
+    }
+
+    pub fn equals(field: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Equals { field: field.into(), value: value.into() }
+    }
+
+    /// Převede dotaz na JSON pro Manticore
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::Match { fields, text, operator: Operator::Or } => {
+                json!({ "match": { fields_key(fields): text } })
+            }
+            Self::Match { fields, text, operator: Operator::And } => {
+                json!({ "match": { fields_key(fields): { "query": text, "operator": "and" } } })
+            }
+            Self::MatchPhrase { fields, phrase } => json!({ "match_phrase": { fields_key(fields): phrase } }),
+            Self::Expression(query) => json!({ "query_string": query }),
+            Self::Equals { field, value } => json!({ "equals": { field.as_str(): value } }),
+            Self::Range(range) => range.to_json(),
+            Self::GeoDistance(geo) => geo.to_json(),
+            Self::Bool(query) => query.to_json(),
+        }
+    }
+}
+
+impl RangeQuery {
+    pub fn new(field: impl Into<String>) -> Self {
+        Self { field: field.into(), ..Self::default() }
+    }
+
+    pub fn gte(mut self, value: impl Into<Value>) -> Self {
+        self.gte = Some(value.into());
+        self
+    }
+
+    pub fn lte(mut self, value: impl Into<Value>) -> Self {
+        self.lte = Some(value.into());
+        self
+    }
+
+    pub fn lt(mut self, value: impl Into<Value>) -> Self {
+        self.lt = Some(value.into());
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        let bounds: Map<String, Value> = [("gte", &self.gte), ("lte", &self.lte), ("lt", &self.lt)]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.clone()?)))
+            .collect();
+        json!({ "range": { self.field.as_str(): bounds } })
+    }
+}
+
+impl From<RangeQuery> for Query {
+    fn from(range: RangeQuery) -> Self {
+        Self::Range(range)
+    }
+}
+
+impl GeoDistance {
+    fn to_json(&self) -> Value {
+        json!({
+            "geo_distance": {
+                "location_anchor": { "lat": self.lat, "lon": self.lon },
+                "location_source": [self.lat_field, self.lon_field],
+                "distance_type": "adaptive",
+                "distance": format!("{} m", self.distance_m),
+            }
+        })
+    }
+}
+
+impl From<GeoDistance> for Query {
+    fn from(geo: GeoDistance) -> Self {
+        Self::GeoDistance(geo)
+    }
+}
+
+impl BoolQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn must(mut self, query: impl Into<Query>) -> Self {
+        self.must.push(query.into());
+        self
+    }
+
+    pub fn should(mut self, query: impl Into<Query>) -> Self {
+        self.should.push(query.into());
+        self
+    }
+
+    pub fn must_not(mut self, query: impl Into<Query>) -> Self {
+        self.must_not.push(query.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.must.is_empty() && self.should.is_empty() && self.must_not.is_empty()
+    }
+
+    /// Jen vylučující podmínky - Manticore takový dotaz odmítne
+    pub fn is_negative_only(&self) -> bool {
+        self.must.is_empty() && self.should.is_empty() && !self.must_not.is_empty()
+    }
+
+    /// Hotový dotaz. Samotné 'must' s jednou podmínkou se
+    /// zjednoduší na tu podmínku (bez obalu 'bool').
+    pub fn build(mut self) -> Query {
+        if self.must.len() == 1 && self.should.is_empty() && self.must_not.is_empty() {
+            return self.must.remove(0);
+        }
+        Query::Bool(self)
+    }
+
+    fn to_json(&self) -> Value {
+        let clauses: Map<String, Value> = [("must", &self.must), ("should", &self.should), ("must_not", &self.must_not)]
+            .into_iter()
+            .filter(|(_, queries)| !queries.is_empty())
+            .map(|(name, queries)| (name.to_string(), queries.iter().map(Query::to_json).collect()))
+            .collect();
+        json!({ "bool": clauses })
+    }
+}
+
+impl From<BoolQuery> for Query {
+    fn from(query: BoolQuery) -> Self {
+        Self::Bool(query)
+    }
+}
+
+/// Klíč pro 'match': pole oddělená čárkou, nebo '_all'
+fn fields_key(fields: &[String]) -> String {
+    if fields.is_empty() {
+        ALL_FIELDS.to_string()
+    } else {
+        fields.join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_on_all_fields() {
+        let query = Query::matches(&[], "senzor teplota", Operator::Or);
+        assert_eq!(query.to_json(), json!({ "match": { "_all": "senzor teplota" } }));
+    }
+
+    #[test]
+    fn match_with_fields_and_operator() {
+        let fields = vec!["title".to_string(), "content".to_string()];
+        let query = Query::matches(&fields, "senzor teplota", Operator::And);
+        assert_eq!(
+            query.to_json(),
+            json!({ "match": { "title,content": { "query": "senzor teplota", "operator": "and" } } })
+        );
+    }
+
+    #[test]
+    fn phrase_and_query_string() {
+        assert_eq!(
+            Query::phrase(&[], "vysoká teplota").to_json(),
+            json!({ "match_phrase": { "_all": "vysoká teplota" } })
+        );
+        assert_eq!(
+            Query::query_string("@title senzor -chyba").to_json(),
+            json!({ "query_string": "@title senzor -chyba" })
+        );
+    }
+
+    #[test]
+    fn range_skips_missing_bounds() {
+        let query: Query = RangeQuery::new("cena").gte(10).lt(20.5).into();
+        assert_eq!(query.to_json(), json!({ "range": { "cena": { "gte": 10, "lt": 20.5 } } }));
+        let query: Query = RangeQuery::new("cena").lte(99).into();
+        assert_eq!(query.to_json(), json!({ "range": { "cena": { "lte": 99 } } }));
+    }
+
+    #[test]
+    fn geo_distance() {
+        let query: Query = GeoDistance {
+            lat_field: "lat".to_string(),
+            lon_field: "lon".to_string(),
+            lat: 50.08,
+            lon: 14.42,
+            distance_m: 1500.0,
+        }
+        .into();
+        assert_eq!(
+            query.to_json(),
+            json!({
+                "geo_distance": {
+                    "location_anchor": { "lat": 50.08, "lon": 14.42 },
+                    "location_source": ["lat", "lon"],
+                    "distance_type": "adaptive",
+                    "distance": "1500 m",
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn bool_nests_and_skips_empty_clauses() {
+        let query = BoolQuery::new()
+            .must(Query::matches(&[], "senzor", Operator::Or))
+            .must_not(BoolQuery::new().should(Query::equals("host", "rpi4")).should(Query::equals("severity", 0)))
+            .build();
+        assert_eq!(
+            query.to_json(),
+            json!({ "bool": {
+                "must": [{ "match": { "_all": "senzor" } }],
+                "must_not": [{ "bool": { "should": [
+                    { "equals": { "host": "rpi4" } },
+                    { "equals": { "severity": 0 } },
+                ] } }],
+            } })
+        );
+    }
+
+    #[test]
+    fn single_must_is_unwrapped() {
+        let inner = Query::query_string("senzor");
+        assert_eq!(BoolQuery::new().must(inner.clone()).build(), inner);
+        assert!(BoolQuery::new().must_not(inner).is_negative_only());
+    }
+}
@@ -1,5 +1,13 @@
 // Zpřístupní modul 'db_postgres', aby ho 'routes' mohly vidět
 pub mod db_postgres;
 pub mod db_valkey;
+pub mod health;
+pub mod manticore_indexer;
+pub mod manticore_query;
 pub mod mqtt_client;
+pub mod mqtt_decode;
+pub mod mqtt_ingest;
+pub mod mqtt_protocol;
+pub mod mqtt_sys;
+pub mod mqtt_topics;
 pub mod search_manticore;
\ No newline at end of file
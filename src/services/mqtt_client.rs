@@ -1,62 +1,295 @@
-use crate::models::MqttStats;
-use crate::state::MqttConfig;
-use rumqttc::{AsyncClient, MqttOptions, QoS};
+use crate::models::{BackendStatus, MqttConnection, MqttPublishRequest, MqttStats};
+use crate::config::MqttConfig;
+use crate::services::mqtt_sys::{self, RateTracker, SYS_FILTER};
+use crate::services::mqtt_protocol::{self, IncomingPublish, MqttClient, MqttEvent, SessionOptions};
+use crate::services::mqtt_topics::{self, TopicTree};
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, watch, RwLock};
+
+/// První pauza před dalším pokusem o připojení k brokeru
+const RETRY_MIN: Duration = Duration::from_secs(1);
+/// Maximální pauza (exponenciálně rosteme až sem)
+const RETRY_MAX: Duration = Duration::from_secs(60);
+
+/// Kapacita kanálu živého výpisu. Odběratel, který zaostane
+/// o víc zpráv, o ty nejstarší přijde ('RecvError::Lagged').
+pub const LIVE_CHANNEL_CAPACITY: usize = 1024;
+
+/// Přijatá zpráva rozesílaná odběratelům živého výpisu (/mqtt/live)
+#[derive(Debug)]
+pub struct LiveMessage {
+    pub packet: IncomingPublish,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Sdílená úložiště, do kterých sběrač zapisuje
+pub struct SubscriberStores {
+    pub stats: watch::Sender<MqttStats>,
+    // Každou přijatou zprávu rozešle všem WebSocket klientům
+    pub live: broadcast::Sender<Arc<LiveMessage>>,
+    pub last_event: Arc<RwLock<Option<DateTime<Utc>>>>,
+    pub topics: Arc<RwLock<TopicTree>>,
+    pub connection: Arc<RwLock<MqttConnection>>,
+}
+
+/// Výchozí stav spojení před prvním pokusem o připojení
+pub fn initial_connection() -> MqttConnection {
+    MqttConnection {
+        status: BackendStatus {
+            connected: false,
+            since: Utc::now(),
+            last_error: None,
+            attempts: 0,
+            next_retry_at: None,
+        },
+        last_message_at: None,
+    }
+}
 
 /// Spustí trvalého MQTT klienta na pozadí (v 'tokio::spawn').
 ///
-/// Tento klient naslouchá $SYS tématům a aktualizuje
-/// sdílený 'stats_store'.
-pub fn launch_mqtt_subscriber(
-    config: MqttConfig,
-    stats_store: Arc<RwLock<MqttStats>>
-) {
+/// Tento klient naslouchá celému $SYS stromu a aktualizuje
+/// sdílené statistiky ve 'watch' kanálu 'stats' - každá změna
+/// probudí odběratele (SSE stream). Do 'last_event' zapisuje čas
+/// každé události z event loopu, aby health kontrola poznala,
+/// že smyčka žije.
+///
+/// Navíc odebírá filtry z 'config.topics' a každou přijatou
+/// zprávu (včetně $SYS) ukládá do stromu 'topics' a rozesílá
+/// do 'live' kanálu pro živý výpis.
+///
+/// Smyčka nikdy nekončí: při chybě spojení zapíše stav do
+/// 'connection', počká (exponenciální odstup) a 'rumqttc' se při
+/// dalším 'poll()' připojí znovu. Po každém CONNACK se odběry
+/// obnoví, protože používáme 'clean_session'.
+///
+/// Vrací 'MqttClient', přes který mohou handlery publikovat
+/// zprávy. Klient jen posílá požadavky do event loopu této úlohy.
+pub fn launch_mqtt_subscriber(config: MqttConfig, stores: SubscriberStores) -> MqttClient {
+    // Nastavíme klienta. Kapacita fronty požadavků musí pojmout
+    // všechny odběry, rezerva navíc je pro zprávy z dashboardu.
+    let (client, mut eventloop) = mqtt_protocol::connect(&config, SessionOptions {
+        client_id: config.client_id.clone(),
+        keep_alive: Duration::from_secs(5),
+        clean_session: true,
+        capacity: 10 + config.topics.len(),
+    });
+    let publisher = client.clone();
+
     // Spustíme úplně novou, nezávislou úlohu na pozadí
     tokio::spawn(async move {
-        // Nastavíme klienta
-        let mut mqtt_options = MqttOptions::new("", config.host, config.port);
-        mqtt_options
-            .set_keep_alive(Duration::from_secs(5))
-            .set_clean_session(true);
+        let mut backoff = RETRY_MIN;
+        // Předchozí vzorky čítačů pro výpočet rychlostí
+        let mut rates = RateTracker::default();
 
-        let (client, mut eventloop) = AsyncClient::new(mqtt_options, 10);
+        // Toto je smyčka, která poběží NAVŽDY
+        // 'eventloop.poll()' se stará o živé připojení a příjem zpráv
+        loop {
+            let notification = match eventloop.poll().await {
+                Ok(notification) => notification,
+                Err(e) => {
+                    tracing::warn!(
+                        "MQTT Sběrač: Spojení ztraceno: {} (další pokus za {} s)",
+                        e, backoff.as_secs()
+                    );
+                    set_failed(&stores.connection, e.to_string(), backoff).await;
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(RETRY_MAX);
+                    continue;
+                }
+            };
 
-        // Přihlásíme se k odběru
-        // Používáme '.unwrap()' - pokud se sběrač nepřihlásí,
-        // je to fatální chyba a má spadnout (a my to uvidíme v logu).
-        client.subscribe("$SYS/broker/uptime", QoS::AtMostOnce).await.unwrap();
-        client.subscribe("$SYS/broker/clients/connected", QoS::AtMostOnce).await.unwrap();
-        client.subscribe("$SYS/broker/messages/sent", QoS::AtMostOnce).await.unwrap();
-        client.subscribe("$SYS/broker/messages/received", QoS::AtMostOnce).await.unwrap();
+            // Jakákoliv událost (i PINGRESP) znamená, že spojení žije
+            *stores.last_event.write().await = Some(Utc::now());
+            // PINGRESP chodí i bez provozu, rychlosti čítačů, které
+            // broker přestal posílat, tak spadnou na nulu
+            stores
+                .stats
+                .send_if_modified(|stats| rates.expire(&mut stats.rates, Instant::now()));
 
-        tracing::info!("MQTT Sběrač: Úspěšně připojen a přihlášen k odběru $SYS.");
+            match notification {
+                MqttEvent::ConnAck { .. } => {
+                    backoff = RETRY_MIN;
+                    // 'try_subscribe' - čekat na místo ve frontě tady nejde,
+                    // frontu vyprazdňuje až další 'poll()' této smyčky
+                    let mut failed = Vec::new();
+                    if let Err(e) = client.try_subscribe(SYS_FILTER, 0) {
+                        failed.push(format!("{}: {}", SYS_FILTER, e));
+                    }
+                    // Filtry pro průzkumník témat. Odebíráme s QoS 2 - broker
+                    // doručí s nižší z obou QoS, takže uvidíme QoS odesílatele.
+                    for filter in &config.topics {
+                        if let Err(e) = client.try_subscribe(filter, 2) {
+                            failed.push(format!("{}: {}", filter, e));
+                        }
+                    }
 
-        // Toto je smyčka, která poběží NAVŽDY
-        // 'eventloop.poll()' se stará o živé připojení a příjem zpráv
-        while let Ok(notification) = eventloop.poll().await {
-            // Zpracujeme jen příchozí zprávy
-            if let rumqttc::Event::Incoming(rumqttc::Packet::Publish(packet)) = notification {
-                let topic = packet.topic;
-                let payload = String::from_utf8(packet.payload.to_vec())
-                                .unwrap_or_else(|_| "N/A".to_string());
-
-                // Získáme zámek pro zápis do našeho sdíleného úložiště
-                let mut stats = stats_store.write().await;
-
-                // Aktualizujeme data
-                match topic.as_str() {
-                    "$SYS/broker/uptime" => stats.uptime = payload,
-                    "$SYS/broker/clients/connected" => stats.clients_connected = payload,
-                    "$SYS/broker/messages/sent" => stats.messages_sent = payload,
-                    "$SYS/broker/messages/received" => stats.messages_received = payload,
-                    _ => {} // Ignorujeme ostatní
+                    if failed.is_empty() {
+                        tracing::info!(
+                            "MQTT Sběrač: Úspěšně připojen a přihlášen k odběru $SYS a {:?}.",
+                            config.topics
+                        );
+                    } else {
+                        tracing::error!("MQTT Sběrač: Některé odběry selhaly: {}", failed.join(", "));
+                    }
+                    set_connected(&stores.connection).await;
                 }
-                // Zámek se zde automaticky uvolní
+
+                // Zpracujeme příchozí zprávy
+                MqttEvent::Publish(packet) => {
+                    stores.connection.write().await.last_message_at = Some(Utc::now());
+                    handle_publish(&stores, &mut rates, packet).await;
+                }
+
+                MqttEvent::Other => {}
             }
         }
-        
-        tracing::warn!("MQTT Sběrač: Smyčka přerušena. Připojení ztraceno.");
     });
-}
\ No newline at end of file
+
+    publisher
+}
+
+/// Uloží zprávu do stromu témat, rozešle ji do živého výpisu
+/// a případně aktualizuje $SYS statistiky
+async fn handle_publish(stores: &SubscriberStores, rates: &mut RateTracker, packet: IncomingPublish) {
+    // Bez odběratelů kanál zprávu jen zahodí. Zpráva se klonuje
+    // levně - payload je 'Bytes' se sdíleným bufferem.
+    if stores.live.receiver_count() > 0 {
+        let _ = stores.live.send(Arc::new(LiveMessage {
+            packet: packet.clone(),
+            received_at: Utc::now(),
+        }));
+    }
+
+    let topic = packet.topic;
+
+    // Každou zprávu si zapamatujeme ve stromu témat
+    stores.topics.write().await.insert(
+        &topic,
+        &packet.payload,
+        packet.qos,
+        packet.retain,
+    );
+
+    if !topic.starts_with("$SYS/") {
+        return;
+    }
+    let payload = String::from_utf8_lossy(&packet.payload);
+
+    // Aktualizujeme data na místě. Odběratele kanálu probudíme
+    // jen tehdy, když se statistiky opravdu změnily.
+    let known = stores
+        .stats
+        .send_if_modified(|stats| mqtt_sys::apply(stats, rates, &topic, &payload));
+    if !known {
+        tracing::trace!("MQTT Sběrač: Neznámé $SYS téma '{}'", topic);
+    }
+}
+
+async fn set_connected(connection: &RwLock<MqttConnection>) {
+    let mut connection = connection.write().await;
+    connection.status = BackendStatus {
+        connected: true,
+        since: Utc::now(),
+        last_error: None,
+        attempts: 0,
+        next_retry_at: None,
+    };
+}
+
+async fn set_failed(connection: &RwLock<MqttConnection>, error: String, retry_in: Duration) {
+    let mut connection = connection.write().await;
+    let status = &mut connection.status;
+    if status.connected {
+        status.since = Utc::now();
+    }
+    status.connected = false;
+    status.last_error = Some(error);
+    status.attempts += 1;
+    status.next_retry_at = chrono::Duration::from_std(retry_in)
+        .ok()
+        .map(|d| Utc::now() + d);
+}
+
+/// Zkontroluje požadavek na publikování a vrátí všechny nalezené chyby.
+///
+/// Paket větší než 'max_packet_size' by klient odmítl až v event
+/// loopu a shodil by tím spojení sběrače - proto ho odmítneme hned.
+pub fn validate_publish(request: &MqttPublishRequest, max_packet_size: usize) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Err(e) = mqtt_topics::validate_topic_name(&request.topic) {
+        errors.push(e);
+    }
+    if request.qos > 2 {
+        errors.push(format!("QoS musí být 0, 1 nebo 2 (zadáno {})", request.qos));
+    }
+    let size = publish_packet_size(&request.topic, request.payload.len());
+    if size > max_packet_size {
+        errors.push(format!(
+            "Zpráva je příliš velká: {} bajtů (limit {} bajtů včetně tématu)",
+            size, max_packet_size
+        ));
+    }
+    errors
+}
+
+/// Horní odhad velikosti PUBLISH paketu: pevná hlavička (typ a až
+/// 4 bajty délky), délka tématu, packet id a délka vlastností (MQTT 5)
+fn publish_packet_size(topic: &str, payload_len: usize) -> usize {
+    1 + 4 + 2 + topic.len() + 2 + 1 + payload_len
+}
+
+/// Odešle zprávu přes klienta sběrače.
+///
+/// Používáme 'try_publish' - když je fronta event loopu plná
+/// (broker neodpovídá), handler nesmí čekat, ale vrátit chybu.
+pub fn publish(client: &MqttClient, request: &MqttPublishRequest) -> Result<(), String> {
+    client.try_publish(
+        &request.topic,
+        request.qos,
+        request.retain,
+        request.payload.clone().into_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: usize = 64 * 1024;
+
+    fn request(topic: &str, payload: String, qos: u8) -> MqttPublishRequest {
+        MqttPublishRequest { topic: topic.to_string(), payload, qos, retain: false }
+    }
+
+    #[test]
+    fn valid_request_passes() {
+        assert!(validate_publish(&request("sensors/kuchyn/temp", "21.5".into(), 1), LIMIT).is_empty());
+        // Prázdný payload maže retained zprávu - je povolený
+        assert!(validate_publish(&request("sensors/kuchyn/temp", String::new(), 0), LIMIT).is_empty());
+    }
+
+    #[test]
+    fn invalid_topic_and_qos_are_reported_together() {
+        let errors = validate_publish(&request("sensors/+/temp", "1".into(), 3), LIMIT);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[1].contains("QoS"));
+        assert_eq!(validate_publish(&request("", "1".into(), 0), LIMIT).len(), 1);
+    }
+
+    #[test]
+    fn oversized_payload_is_rejected() {
+        let topic = "sensors/big";
+        let fits = LIMIT - publish_packet_size(topic, 0);
+        assert!(validate_publish(&request(topic, "x".repeat(fits), 1), LIMIT).is_empty());
+
+        let errors = validate_publish(&request(topic, "x".repeat(fits + 1), 1), LIMIT);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("příliš velká"));
+        // Do limitu se počítá i téma
+        let long_topic = "t".repeat(100);
+        assert!(!validate_publish(&request(&long_topic, "x".repeat(fits), 1), LIMIT).is_empty());
+    }
+}
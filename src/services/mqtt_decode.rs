@@ -0,0 +1,407 @@
+use crate::config::DecoderRule;
+use crate::services::mqtt_topics::TopicFilter;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Výsledek dekódování payloadu
+#[derive(Debug, Clone)]
+pub struct Decoded {
+    // Název dekodéru, který payload opravdu dekódoval
+    pub decoder: &'static str,
+    // Text pro zobrazení v průzkumníku a živém výpisu
+    pub text: String,
+    // Strukturovaná hodnota pro další zpracování (ingest, JSONPath).
+    // 'None' má jen hex výpis - z něj žádnou hodnotu nevytáhneme.
+    pub value: Option<Value>,
+    // Proč selhal dekodér nastavený pro téma ('text' je pak z 'auto')
+    pub error: Option<String>,
+}
+
+/// Dekodér payloadu MQTT zprávy.
+///
+/// Vlastní dekodér stačí zaregistrovat v 'DecoderRegistry::new()'
+/// a v konfiguraci ho pak jde přiřadit tématům podle názvu.
+pub trait PayloadDecoder: Send + Sync {
+    /// Název pro konfiguraci ('[[mqtt.decoders]] decoder = "..."')
+    fn name(&self) -> &'static str;
+
+    fn decode(&self, payload: &[u8]) -> Result<Decoded, String>;
+}
+
+/// Text v UTF-8
+struct Utf8Decoder;
+
+impl PayloadDecoder for Utf8Decoder {
+    fn name(&self) -> &'static str {
+        "utf8"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Decoded, String> {
+        let text = std::str::from_utf8(payload)
+            .map_err(|e| format!("payload není UTF-8: {}", e))?;
+        Ok(decoded(self.name(), text.to_string(), Some(Value::String(text.to_string()))))
+    }
+}
+
+/// JSON dokument
+struct JsonDecoder;
+
+impl PayloadDecoder for JsonDecoder {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Decoded, String> {
+        let value: Value = serde_json::from_slice(payload)
+            .map_err(|e| format!("payload není JSON: {}", e))?;
+        Ok(decoded(self.name(), value.to_string(), Some(value)))
+    }
+}
+
+/// Číslo jako text ("21.5", " 42\n")
+struct NumberDecoder;
+
+impl PayloadDecoder for NumberDecoder {
+    fn name(&self) -> &'static str {
+        "number"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Decoded, String> {
+        let text = std::str::from_utf8(payload)
+            .map_err(|_| "payload není UTF-8".to_string())?
+            .trim();
+        let number = text
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .ok_or_else(|| format!("payload '{}' není konečné číslo", text))?;
+        // Celá čísla necháme celá (bez ".0")
+        let number = text.parse::<i64>().map(serde_json::Number::from).unwrap_or(number);
+        Ok(decoded(self.name(), number.to_string(), Some(Value::Number(number))))
+    }
+}
+
+/// Hex výpis - funguje pro jakákoliv data
+struct HexDecoder;
+
+impl PayloadDecoder for HexDecoder {
+    fn name(&self) -> &'static str {
+        "hex"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Decoded, String> {
+        Ok(decoded(self.name(), hex_dump(payload), None))
+    }
+}
+
+/// CBOR (RFC 8949), zobrazený jako JSON
+struct CborDecoder;
+
+impl PayloadDecoder for CborDecoder {
+    fn name(&self) -> &'static str {
+        "cbor"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Decoded, String> {
+        let cbor: ciborium::Value = ciborium::de::from_reader(payload)
+            .map_err(|e| format!("payload není CBOR: {}", e))?;
+        let value = cbor_to_json(cbor);
+        Ok(decoded(self.name(), value.to_string(), Some(value)))
+    }
+}
+
+/// Výchozí dekodér: číslo, JSON, text, a když nic z toho, hex výpis
+struct AutoDecoder;
+
+impl PayloadDecoder for AutoDecoder {
+    fn name(&self) -> &'static str {
+        "auto"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Decoded, String> {
+        NumberDecoder
+            .decode(payload)
+            .or_else(|_| JsonDecoder.decode(payload))
+            .or_else(|_| Utf8Decoder.decode(payload))
+            .or_else(|_| HexDecoder.decode(payload))
+    }
+}
+
+fn decoded(decoder: &'static str, text: String, value: Option<Value>) -> Decoded {
+    Decoded { decoder, text, value, error: None }
+}
+
+/// Registr dekodérů a pravidel, který dekodér použít pro které téma.
+///
+/// Pravidla se zkouší v pořadí z konfigurace, vyhrává první shoda.
+/// Téma bez pravidla dekóduje 'auto'.
+pub struct DecoderRegistry {
+    decoders: BTreeMap<&'static str, Arc<dyn PayloadDecoder>>,
+    rules: Vec<(TopicFilter, Arc<dyn PayloadDecoder>)>,
+    fallback: Arc<dyn PayloadDecoder>,
+}
+
+impl Default for DecoderRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecoderRegistry {
+    /// Registr se všemi vestavěnými dekodéry, zatím bez pravidel
+    pub fn new() -> Self {
+        let mut registry = Self {
+            decoders: BTreeMap::new(),
+            rules: Vec::new(),
+            fallback: Arc::new(AutoDecoder),
+        };
+        registry.register(Arc::new(AutoDecoder));
+        registry.register(Arc::new(Utf8Decoder));
+        registry.register(Arc::new(JsonDecoder));
+        registry.register(Arc::new(NumberDecoder));
+        registry.register(Arc::new(HexDecoder));
+        registry.register(Arc::new(CborDecoder));
+        registry
+    }
+
+    /// Registr s pravidly z konfigurace. Ta je už zvalidovaná
+    /// ('add_rule' při načítání), chyba by tu znamenala bug.
+    pub fn from_config(rules: &[DecoderRule]) -> Self {
+        let mut registry = Self::new();
+        for rule in rules {
+            registry
+                .add_rule(rule)
+                .expect("Pravidla dekodérů mají být zvalidovaná");
+        }
+        registry
+    }
+
+    /// Přidá (nebo nahradí) dekodér pod jeho názvem
+    pub fn register(&mut self, decoder: Arc<dyn PayloadDecoder>) {
+        self.decoders.insert(decoder.name(), decoder);
+    }
+
+    /// Přidá pravidlo "téma -> dekodér" na konec seznamu
+    pub fn add_rule(&mut self, rule: &DecoderRule) -> Result<(), String> {
+        let filter = TopicFilter::parse(&rule.topic)?;
+        let decoder = self.decoders.get(rule.decoder.as_str()).cloned().ok_or_else(|| {
+            format!(
+                "neznámý dekodér '{}' (dostupné: {})",
+                rule.decoder,
+                self.names().join(", ")
+            )
+        })?;
+        self.rules.push((filter, decoder));
+        Ok(())
+    }
+
+    /// Názvy registrovaných dekodérů
+    pub fn names(&self) -> Vec<&'static str> {
+        self.decoders.keys().copied().collect()
+    }
+
+    /// Dekóduje payload dekodérem nastaveným pro téma.
+    ///
+    /// Nikdy neselže: když nastavený dekodér payload nezvládne,
+    /// použije se 'auto' a důvod se vrátí v 'error'.
+    pub fn decode(&self, topic: &str, payload: &[u8]) -> Decoded {
+        let decoder = self
+            .rules
+            .iter()
+            .find(|(filter, _)| filter.matches(topic))
+            .map_or(&self.fallback, |(_, decoder)| decoder);
+
+        decoder.decode(payload).unwrap_or_else(|error| {
+            // 'auto' končí hex výpisem, který nikdy neselže
+            let mut fallback = AutoDecoder
+                .decode(payload)
+                .unwrap_or_else(|_| decoded("hex", hex_dump(payload), None));
+            fallback.error = Some(format!("{}: {}", decoder.name(), error));
+            fallback
+        })
+    }
+}
+
+/// Hex výpis po 16 bajtech: offset, bajty a tisknutelné ASCII znaky
+fn hex_dump(payload: &[u8]) -> String {
+    let mut out = String::with_capacity(payload.len() * 4 + 16);
+    for (index, chunk) in payload.chunks(16).enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        let _ = write!(out, "{:08x} ", index * 16);
+        for byte in chunk {
+            let _ = write!(out, " {:02x}", byte);
+        }
+        // Zarovnání ASCII sloupce u posledního neúplného řádku
+        out.push_str(&"   ".repeat(16 - chunk.len()));
+        out.push_str("  |");
+        out.extend(chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }));
+        out.push('|');
+    }
+    out
+}
+
+/// Převede CBOR hodnotu na JSON. Bajty se zobrazí jako hex řetězec,
+/// tagy se vynechají a neřetězcové klíče map se převedou na text.
+fn cbor_to_json(value: ciborium::Value) -> Value {
+    use ciborium::Value as Cbor;
+    match value {
+        Cbor::Integer(i) => {
+            let i = i128::from(i);
+            i64::try_from(i)
+                .map(Value::from)
+                .or_else(|_| u64::try_from(i).map(Value::from))
+                .unwrap_or_else(|_| Value::String(i.to_string()))
+        }
+        Cbor::Float(f) => serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number),
+        Cbor::Bytes(bytes) => Value::String(bytes.iter().map(|b| format!("{:02x}", b)).collect()),
+        Cbor::Text(text) => Value::String(text),
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Tag(_, inner) => cbor_to_json(*inner),
+        Cbor::Array(items) => Value::Array(items.into_iter().map(cbor_to_json).collect()),
+        Cbor::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        Cbor::Text(text) => text,
+                        other => cbor_to_json(other).to_string(),
+                    };
+                    (key, cbor_to_json(value))
+                })
+                .collect(),
+        ),
+        // 'Null' a případné budoucí varianty
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(topic: &str, decoder: &str) -> DecoderRule {
+        DecoderRule { topic: topic.to_string(), decoder: decoder.to_string() }
+    }
+
+    fn cbor(value: &ciborium::Value) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(value, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn number_decoder_keeps_integers() {
+        let decoded = NumberDecoder.decode(b" 42\n").unwrap();
+        assert_eq!(decoded.value, Some(json!(42)));
+        assert_eq!(decoded.text, "42");
+
+        let decoded = NumberDecoder.decode(b"-21.5").unwrap();
+        assert_eq!(decoded.value, Some(json!(-21.5)));
+
+        assert!(NumberDecoder.decode(b"NaN").is_err());
+        assert!(NumberDecoder.decode(b"inf").is_err());
+        assert!(NumberDecoder.decode(b"21 C").is_err());
+        assert!(NumberDecoder.decode(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn json_decoder() {
+        let decoded = JsonDecoder.decode(br#"{"temp": 21.5, "ok": true}"#).unwrap();
+        assert_eq!(decoded.decoder, "json");
+        assert_eq!(decoded.value, Some(json!({ "temp": 21.5, "ok": true })));
+        assert!(JsonDecoder.decode(b"{temp: 1}").is_err());
+    }
+
+    #[test]
+    fn hex_dump_aligns_ascii_column() {
+        assert_eq!(hex_dump(b""), "");
+        assert_eq!(
+            hex_dump(b"AB\x00"),
+            format!("00000000  41 42 00{}  |AB.|", "   ".repeat(13))
+        );
+
+        let dump = hex_dump(&[b'x'; 17]);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("|xxxxxxxxxxxxxxxx|"));
+        assert!(lines[1].starts_with("00000010  78"));
+        // ASCII sloupec začíná na obou řádcích na stejném místě
+        assert_eq!(lines[0].find('|'), lines[1].find('|'));
+    }
+
+    #[test]
+    fn cbor_to_json_converts_values() {
+        use ciborium::Value as Cbor;
+        let value = Cbor::Map(vec![
+            (Cbor::Text("temp".into()), Cbor::Float(21.5)),
+            (Cbor::Integer(7.into()), Cbor::Bytes(vec![0xde, 0xad])),
+            (Cbor::Text("tag".into()), Cbor::Tag(1, Box::new(Cbor::Integer(1_700_000_000.into())))),
+            (Cbor::Text("list".into()), Cbor::Array(vec![Cbor::Bool(true), Cbor::Null])),
+            (Cbor::Text("big".into()), Cbor::Integer(u64::MAX.into())),
+            (Cbor::Text("nan".into()), Cbor::Float(f64::NAN)),
+        ]);
+        assert_eq!(
+            cbor_to_json(value),
+            json!({
+                "temp": 21.5,
+                "7": "dead",
+                "tag": 1_700_000_000,
+                "list": [true, null],
+                "big": u64::MAX,
+                "nan": null,
+            })
+        );
+
+        let decoded = CborDecoder.decode(&cbor(&Cbor::Text("ahoj".into()))).unwrap();
+        assert_eq!(decoded.value, Some(json!("ahoj")));
+        assert!(CborDecoder.decode(&[0xff, 0xff]).is_err());
+    }
+
+    #[test]
+    fn auto_tries_number_json_text_then_hex() {
+        assert_eq!(AutoDecoder.decode(b"12").unwrap().decoder, "number");
+        assert_eq!(AutoDecoder.decode(b"[1, 2]").unwrap().decoder, "json");
+        assert_eq!(AutoDecoder.decode(b"zapnuto").unwrap().decoder, "utf8");
+
+        let decoded = AutoDecoder.decode(&[0xff, 0x00]).unwrap();
+        assert_eq!(decoded.decoder, "hex");
+        assert_eq!(decoded.value, None);
+    }
+
+    #[test]
+    fn registry_uses_first_matching_rule() {
+        let registry = DecoderRegistry::from_config(&[
+            rule("sensors/+/raw", "hex"),
+            rule("sensors/#", "utf8"),
+        ]);
+        assert_eq!(registry.decode("sensors/a/raw", b"12").decoder, "hex");
+        assert_eq!(registry.decode("sensors/a/temp", b"12").decoder, "utf8");
+        // Téma bez pravidla dekóduje 'auto'
+        assert_eq!(registry.decode("jine", b"12").decoder, "number");
+    }
+
+    #[test]
+    fn failed_decoder_falls_back_to_auto() {
+        let registry = DecoderRegistry::from_config(&[rule("t", "json")]);
+        let decoded = registry.decode("t", b"21.5");
+        assert_eq!(decoded.decoder, "json");
+
+        let decoded = registry.decode("t", b"21.5 C");
+        assert_eq!(decoded.decoder, "utf8");
+        assert_eq!(decoded.text, "21.5 C");
+        assert!(decoded.error.as_deref().unwrap().starts_with("json: "));
+    }
+
+    #[test]
+    fn unknown_decoder_is_rejected() {
+        let mut registry = DecoderRegistry::new();
+        let error = registry.add_rule(&rule("t", "protobuf")).unwrap_err();
+        assert!(error.contains("protobuf"));
+        assert!(registry.add_rule(&rule("a/#/b", "json")).is_err());
+    }
+}
@@ -0,0 +1,432 @@
+use crate::config::{IngestConfig, IngestRule, MqttConfig};
+use crate::models::{IngestStats, SenzorData};
+use crate::services::db_postgres;
+use crate::services::mqtt_decode::{Decoded, DecoderRegistry};
+use crate::services::mqtt_protocol::{self, MqttEvent, SessionOptions};
+use crate::services::mqtt_topics::TopicFilter;
+use crate::state::Backend;
+use chrono::Utc;
+use serde_json_path::JsonPath;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+use tokio::time::Instant;
+
+/// Keep-alive ingest spojení. Při zpětném tlaku event loop
+/// chvíli nepolluje, proto je delší než u sběrače statistik.
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+/// Pauzy mezi pokusy o nové připojení / zápis dávky
+const RETRY_MIN: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(30);
+
+/// Pravidlo připravené k použití (rozparsovaný filtr a JSONPath)
+struct CompiledRule {
+    filter: TopicFilter,
+    senzor_id: String,
+    json_path: Option<JsonPath>,
+}
+
+/// Ověří pravidlo z konfigurace (filtr, JSONPath, zástupné symboly v 'senzor_id')
+pub fn validate_rule(rule: &IngestRule) -> Result<(), String> {
+    compile_rule(rule).map(|_| ())
+}
+
+fn compile_rule(rule: &IngestRule) -> Result<CompiledRule, String> {
+    let filter = TopicFilter::parse(&rule.topic)?;
+
+    let wildcards = filter.wildcards();
+    for placeholder in placeholders(&rule.senzor_id) {
+        if placeholder == "topic" {
+            continue;
+        }
+        match placeholder.parse::<usize>() {
+            Ok(n) if (1..=wildcards).contains(&n) => {}
+            _ => {
+                return Err(format!(
+                    "senzor_id '{}': neznámý zástupný symbol '{{{}}}' (filtr má {} zástupných úrovní)",
+                    rule.senzor_id, placeholder, wildcards
+                ))
+            }
+        }
+    }
+
+    let json_path = match &rule.json_path {
+        Some(path) => Some(
+            JsonPath::parse(path).map_err(|e| format!("json_path '{}': {}", path, e))?,
+        ),
+        None => None,
+    };
+
+    Ok(CompiledRule {
+        filter,
+        senzor_id: rule.senzor_id.clone(),
+        json_path,
+    })
+}
+
+/// Názvy zástupných symbolů '{...}' v šabloně
+fn placeholders(template: &str) -> impl Iterator<Item = &str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+}
+
+/// Dosadí téma a zachycené úrovně do šablony 'senzor_id'
+fn render_senzor_id(template: &str, topic: &str, captures: &[String]) -> String {
+    let mut result = template.replace("{topic}", topic);
+    for (index, capture) in captures.iter().enumerate() {
+        result = result.replace(&format!("{{{}}}", index + 1), capture);
+    }
+    result
+}
+
+/// Vytáhne číselnou hodnotu z dekódovaného payloadu - buď celou
+/// hodnotu, nebo hodnotu na JSONPath (číslo nebo řetězec s číslem).
+fn extract_value(decoded: &Decoded, json_path: Option<&JsonPath>) -> Result<f64, String> {
+    if let Some(error) = &decoded.error {
+        return Err(error.clone());
+    }
+    let value = decoded
+        .value
+        .as_ref()
+        .ok_or_else(|| format!("dekodér '{}' nevrací hodnotu", decoded.decoder))?;
+
+    let value = match json_path {
+        Some(path) => path
+            .query(value)
+            .exactly_one()
+            .map_err(|_| format!("JSONPath '{}' nevrátil právě jednu hodnotu", path))?,
+        None => value,
+    };
+    match value {
+        serde_json::Value::Number(n) => n.as_f64().ok_or_else(|| "číslo mimo rozsah".to_string()),
+        serde_json::Value::String(s) => s
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("hodnota '{}' není číslo", s)),
+        other => Err(format!("hodnota {} není číslo", other)),
+    }
+}
+
+/// Najde první pravidlo, které odpovídá tématu, a převede zprávu na řádek.
+///
+/// 'Ok(None)' znamená, že téma nepatří žádnému pravidlu.
+fn map_message(
+    rules: &[CompiledRule],
+    decoders: &DecoderRegistry,
+    topic: &str,
+    payload: &[u8],
+) -> Result<Option<SenzorData>, String> {
+    for rule in rules {
+        if let Some(captures) = rule.filter.captures(topic) {
+            let decoded = decoders.decode(topic, payload);
+            let hodnota = extract_value(&decoded, rule.json_path.as_ref())?;
+            return Ok(Some(SenzorData {
+                time: Utc::now(),
+                senzor_id: render_senzor_id(&rule.senzor_id, topic, &captures),
+                hodnota,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Spustí most MQTT -> Timescale jako dvě úlohy na pozadí.
+///
+/// Čtenář drží vlastní MQTT spojení ('clean_session = false', QoS 1)
+/// a namapované řádky posílá do omezené fronty. Payload dekóduje
+/// stejný registr dekodérů jako průzkumník témat. Zapisovač z fronty
+/// skládá dávky a zapisuje je jedním INSERTem. Když databáze nestíhá
+/// nebo není dostupná, fronta se zaplní, čtenář přestane číst
+/// z brokeru a další zprávy zatím drží broker v naší session.
+///
+/// 'rumqttc' potvrzuje (PUBACK) zprávu hned při přijetí. Řádky, které
+/// už jsou ve frontě nebo v rozpracované dávce, proto při pádu nebo
+/// restartu aplikace zmizí - pro ně platí "nejvýš jednou".
+///
+/// Zpráva větší než 'mqtt.max_packet_size' shodí spojení ještě před
+/// potvrzením a broker ji v naší session doručí znovu - u MQTT 3.1.1
+/// proto limit musí pokrýt největší zprávu na ingest tématech.
+/// MQTT 5 limit oznámí brokeru, který takové zprávy rovnou zahodí.
+pub fn launch_ingest(
+    mqtt: &MqttConfig,
+    config: IngestConfig,
+    decoders: Arc<DecoderRegistry>,
+    pool: Backend<PgPool>,
+    stats: Arc<RwLock<IngestStats>>,
+) {
+    // Konfigurace je už zvalidovaná, chyba by tu znamenala bug
+    let rules: Vec<CompiledRule> = config
+        .rules
+        .iter()
+        .map(|rule| compile_rule(rule).expect("Pravidla ingestu mají být zvalidovaná"))
+        .collect();
+    let mut filters: Vec<String> = config.rules.iter().map(|r| r.topic.clone()).collect();
+    filters.sort();
+    filters.dedup();
+
+    let (tx, rx) = mpsc::channel(config.queue_capacity);
+
+    // Přihlášení, TLS a verze protokolu jsou stejné jako u sběrače
+    let (client, mut eventloop) = mqtt_protocol::connect(mqtt, SessionOptions {
+        client_id: config.client_id.clone(),
+        keep_alive: KEEP_ALIVE,
+        clean_session: false,
+        capacity: 10 + filters.len(),
+    });
+
+    // ---- Čtenář: MQTT -> fronta ----
+    let reader_stats = stats.clone();
+    tokio::spawn(async move {
+        let mut backoff = RETRY_MIN;
+        loop {
+            match eventloop.poll().await {
+                Ok(MqttEvent::ConnAck { session_present }) => {
+                    backoff = RETRY_MIN;
+                    // Broker si naši session nepamatuje (první start,
+                    // restart brokeru) - musíme se přihlásit znovu
+                    if !session_present {
+                        for filter in &filters {
+                            if let Err(e) = client.subscribe(filter, 1).await {
+                                tracing::error!("MQTT Ingest: Nelze odebírat '{}': {}", filter, e);
+                            }
+                        }
+                    }
+                    tracing::info!("MQTT Ingest: Připojeno, odebírám {:?}.", filters);
+                }
+                // Retained zprávy posílá broker při každém přihlášení -
+                // jde o starý stav, který by v tabulce vytvořil duplicity
+                Ok(MqttEvent::Publish(packet)) if packet.retain => {}
+                Ok(MqttEvent::Publish(packet)) => {
+                    match map_message(&rules, &decoders, &packet.topic, &packet.payload) {
+                        Ok(Some(row)) => {
+                            reader_stats.write().await.received += 1;
+                            // Zpětný tlak: plná fronta tady čtenáře zastaví
+                            if tx.send(row).await.is_err() {
+                                tracing::error!("MQTT Ingest: Zapisovač skončil, ukončuji čtení.");
+                                return;
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            tracing::debug!("MQTT Ingest: Zpráva z '{}' přeskočena: {}", packet.topic, e);
+                            reader_stats.write().await.skipped += 1;
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!(
+                        "MQTT Ingest: Chyba spojení: {} (další pokus za {} s)",
+                        e, backoff.as_secs()
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(RETRY_MAX);
+                }
+            }
+        }
+    });
+
+    // ---- Zapisovač: fronta -> Postgres ----
+    let batch_size = config.batch_size;
+    let flush_interval = Duration::from_millis(config.flush_interval_ms);
+    tokio::spawn(async move {
+        let mut rx = rx;
+        let mut batch = Vec::with_capacity(batch_size);
+        // Čekáme na první řádek, pak dávku plníme nejdéle 'flush_interval'
+        while let Some(first) = rx.recv().await {
+            batch.push(first);
+            let deadline = Instant::now() + flush_interval;
+            while batch.len() < batch_size {
+                match tokio::time::timeout_at(deadline, rx.recv()).await {
+                    Ok(Some(row)) => batch.push(row),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+            flush(&pool, &mut batch, &stats).await;
+        }
+    });
+}
+
+/// Chyba, kterou opakování nespraví - databáze řádky odmítla
+/// (porušené omezení, špatný typ, chybějící tabulka...).
+///
+/// Chyby spojení a serveru, který zrovna nemůže ('08' spojení,
+/// '53' nedostatek prostředků, '57' vypínání, '40' konflikt
+/// transakcí), jsou přechodné a dávka se zkusí zapsat znovu.
+fn is_permanent(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(db) => !db
+            .code()
+            .is_some_and(|code| ["08", "40", "53", "57"].iter().any(|class| code.starts_with(class))),
+        _ => false,
+    }
+}
+
+/// Zapíše dávku. Při přechodné chybě (databáze nedostupná, spadlé
+/// spojení) to zkouší znovu, dokud se zápis nepovede - mezitím se
+/// plní fronta a čtenář přestane brát zprávy z brokeru.
+///
+/// Když databáze dávku odmítne, bývá na vině jeden vadný řádek.
+/// Řádky se pak zapíšou po jednom a zahodí se (a započítají do
+/// 'dropped') jen ty, které databáze odmítne i samotné.
+async fn flush(pool: &Backend<PgPool>, batch: &mut Vec<SenzorData>, stats: &RwLock<IngestStats>) {
+    let mut dropped = 0;
+    if let Err(e) = write_rows(pool, batch, stats).await {
+        tracing::warn!(
+            "MQTT Ingest: Databáze odmítla dávku {} řádků ({}), zapisuji po jednom.",
+            batch.len(), e
+        );
+        let mut last_error = None;
+        for row in batch.iter() {
+            if let Err(e) = write_rows(pool, std::slice::from_ref(row), stats).await {
+                tracing::error!("MQTT Ingest: Databáze odmítla řádek {:?}, zahazuji ho: {}", row, e);
+                dropped += 1;
+                last_error = Some(e);
+            }
+        }
+        let mut stats = stats.write().await;
+        stats.dropped += dropped;
+        if let Some(e) = last_error {
+            stats.last_error = Some(format!("zahozeno {} z {} řádků: {}", dropped, batch.len(), e));
+        }
+    }
+
+    if dropped < batch.len() as u64 {
+        let mut stats = stats.write().await;
+        stats.batches += 1;
+        stats.last_flush_at = Some(Utc::now());
+    }
+    batch.clear();
+}
+
+/// Zapíše řádky jedním INSERTem, přechodné chyby opakuje.
+/// Chybu vrací jen tehdy, když databáze řádky odmítla.
+async fn write_rows(
+    pool: &Backend<PgPool>,
+    rows: &[SenzorData],
+    stats: &RwLock<IngestStats>,
+) -> Result<(), String> {
+    let mut backoff = RETRY_MIN;
+    loop {
+        let result = match pool.get().await {
+            Ok(pool) => db_postgres::insert_senzor_data(&pool, rows)
+                .await
+                .map_err(|e| (is_permanent(&e), e.to_string())),
+            Err(status) => Err((false, status.summary())),
+        };
+
+        let mut stats = stats.write().await;
+        match result {
+            Ok(inserted) => {
+                stats.inserted += inserted;
+                stats.last_error = None;
+                return Ok(());
+            }
+            Err((true, e)) => return Err(e),
+            Err((false, e)) => {
+                tracing::warn!(
+                    "MQTT Ingest: Zápis {} řádků selhal: {} (další pokus za {} s)",
+                    rows.len(), e, backoff.as_secs()
+                );
+                stats.last_error = Some(e);
+            }
+        }
+        drop(stats);
+
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(RETRY_MAX);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DecoderRule;
+
+    fn rule(topic: &str, senzor_id: &str, json_path: Option<&str>) -> CompiledRule {
+        compile_rule(&IngestRule {
+            topic: topic.to_string(),
+            senzor_id: senzor_id.to_string(),
+            json_path: json_path.map(str::to_string),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn senzor_id_template_uses_topic_and_captures() {
+        let captures = vec!["kuchyn".to_string(), "temp".to_string()];
+        assert_eq!(render_senzor_id("{topic}", "sensors/kuchyn/temp", &[]), "sensors/kuchyn/temp");
+        assert_eq!(render_senzor_id("{1}-{2}", "sensors/kuchyn/temp", &captures), "kuchyn-temp");
+        assert_eq!(render_senzor_id("pevny", "sensors/kuchyn/temp", &captures), "pevny");
+    }
+
+    #[test]
+    fn rule_with_unknown_placeholder_is_rejected() {
+        let rule = |senzor_id: &str| IngestRule {
+            topic: "sensors/+/temp".to_string(),
+            senzor_id: senzor_id.to_string(),
+            json_path: None,
+        };
+        assert!(validate_rule(&rule("{1}")).is_ok());
+        assert!(validate_rule(&rule("{2}")).is_err());
+        assert!(validate_rule(&rule("{nazev}")).is_err());
+    }
+
+    #[test]
+    fn value_from_number_json_path_and_numeric_string() {
+        let decoders = DecoderRegistry::new();
+        let path = JsonPath::parse("$.temp").unwrap();
+
+        let decoded = decoders.decode("t", b"21.5");
+        assert_eq!(extract_value(&decoded, None), Ok(21.5));
+
+        let decoded = decoders.decode("t", br#"{"temp": 19, "hum": 40}"#);
+        assert_eq!(extract_value(&decoded, Some(&path)), Ok(19.0));
+
+        let decoded = decoders.decode("t", br#"{"temp": " 7.25 "}"#);
+        assert_eq!(extract_value(&decoded, Some(&path)), Ok(7.25));
+    }
+
+    #[test]
+    fn value_errors() {
+        let decoders = DecoderRegistry::new();
+        let path = JsonPath::parse("$.temp").unwrap();
+
+        // Objekt bez JSONPath, chybějící klíč, text, hex bez hodnoty
+        assert!(extract_value(&decoders.decode("t", br#"{"temp": 1}"#), None).is_err());
+        assert!(extract_value(&decoders.decode("t", br#"{"hum": 1}"#), Some(&path)).is_err());
+        assert!(extract_value(&decoders.decode("t", b"zapnuto"), None).is_err());
+        assert!(extract_value(&decoders.decode("t", &[0xff, 0xfe]), None).is_err());
+
+        // Selhal dekodér nastavený pro téma - hodnotu z 'auto' nebereme
+        let mut decoders = DecoderRegistry::new();
+        decoders
+            .add_rule(&DecoderRule { topic: "t".to_string(), decoder: "json".to_string() })
+            .unwrap();
+        let decoded = decoders.decode("t", b"42 C");
+        assert!(decoded.error.is_some());
+        assert!(extract_value(&decoded, None).is_err());
+    }
+
+    #[test]
+    fn message_is_mapped_by_first_matching_rule() {
+        let rules = [
+            rule("sensors/+/temp", "{1}", None),
+            rule("sensors/#", "ostatni/{1}", Some("$.v")),
+        ];
+        let decoders = DecoderRegistry::new();
+
+        let row = map_message(&rules, &decoders, "sensors/kuchyn/temp", b"21.5").unwrap().unwrap();
+        assert_eq!((row.senzor_id.as_str(), row.hodnota), ("kuchyn", 21.5));
+
+        let row = map_message(&rules, &decoders, "sensors/sklep/hum", br#"{"v": 80}"#).unwrap().unwrap();
+        assert_eq!((row.senzor_id.as_str(), row.hodnota), ("ostatni/sklep/hum", 80.0));
+
+        assert!(map_message(&rules, &decoders, "jine/tema", b"1").unwrap().is_none());
+        // První shoda vyhrává, i když z ní hodnota nejde vytáhnout
+        assert!(map_message(&rules, &decoders, "sensors/kuchyn/temp", b"{}").is_err());
+    }
+}
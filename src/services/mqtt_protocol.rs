@@ -0,0 +1,300 @@
+use crate::config::{MqttConfig, MqttProtocol, MqttTlsConfig};
+use bytes::Bytes;
+use rumqttc::v5;
+use rumqttc::v5::mqttbytes::v5::{ConnectProperties, Packet as PacketV5};
+use rumqttc::{Event, Packet, TlsConfiguration, Transport};
+use std::path::Path;
+use std::time::Duration;
+
+/// Nastavení session, které se liší podle účelu klienta
+/// (sběrač statistik vs. ingest)
+pub struct SessionOptions {
+    pub client_id: String,
+    pub keep_alive: Duration,
+    // 'false' = broker drží odběry a zprávy, i když jsme odpojení
+    pub clean_session: bool,
+    // Kapacita fronty požadavků (odběry, publikování)
+    pub capacity: usize,
+}
+
+/// Přijatá PUBLISH zpráva, stejná pro MQTT 3.1.1 i 5
+#[derive(Debug, Clone)]
+pub struct IncomingPublish {
+    pub topic: String,
+    pub payload: Bytes,
+    pub qos: u8,
+    pub retain: bool,
+}
+
+/// Události z event loopu, které nás zajímají
+pub enum MqttEvent {
+    ConnAck { session_present: bool },
+    Publish(IncomingPublish),
+    // PINGRESP, SUBACK, odchozí pakety...
+    Other,
+}
+
+/// Klient nezávislý na verzi protokolu.
+/// Jen posílá požadavky do event loopu ('MqttEventLoop').
+#[derive(Clone)]
+pub enum MqttClient {
+    V311(rumqttc::AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+impl MqttClient {
+    /// Odběr bez čekání na místo ve frontě (lze volat i z event loopu)
+    pub fn try_subscribe(&self, filter: &str, qos: u8) -> Result<(), String> {
+        match self {
+            Self::V311(client) => client.try_subscribe(filter, qos_v311(qos)).map_err(|e| e.to_string()),
+            Self::V5(client) => client.try_subscribe(filter, qos_v5(qos)).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Odběr, který počká na místo ve frontě požadavků
+    pub async fn subscribe(&self, filter: &str, qos: u8) -> Result<(), String> {
+        match self {
+            Self::V311(client) => client.subscribe(filter, qos_v311(qos)).await.map_err(|e| e.to_string()),
+            Self::V5(client) => client.subscribe(filter, qos_v5(qos)).await.map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn try_publish(&self, topic: &str, qos: u8, retain: bool, payload: Vec<u8>) -> Result<(), String> {
+        match self {
+            Self::V311(client) => client
+                .try_publish(topic, qos_v311(qos), retain, payload)
+                .map_err(|e| e.to_string()),
+            Self::V5(client) => client
+                .try_publish(topic, qos_v5(qos), retain, payload)
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Event loop nezávislý na verzi protokolu.
+/// Žije po celou dobu běhu, velikost variant nehraje roli.
+#[allow(clippy::large_enum_variant)]
+pub enum MqttEventLoop {
+    V311(rumqttc::EventLoop),
+    V5(v5::EventLoop),
+}
+
+impl MqttEventLoop {
+    /// Viz 'rumqttc::EventLoop::poll' - po chybě se další
+    /// volání pokusí připojit znovu.
+    pub async fn poll(&mut self) -> Result<MqttEvent, String> {
+        match self {
+            Self::V311(eventloop) => match eventloop.poll().await.map_err(|e| e.to_string())? {
+                Event::Incoming(Packet::ConnAck(ack)) => Ok(MqttEvent::ConnAck {
+                    session_present: ack.session_present,
+                }),
+                Event::Incoming(Packet::Publish(packet)) => Ok(MqttEvent::Publish(IncomingPublish {
+                    topic: packet.topic,
+                    payload: packet.payload,
+                    qos: packet.qos as u8,
+                    retain: packet.retain,
+                })),
+                _ => Ok(MqttEvent::Other),
+            },
+            Self::V5(eventloop) => match eventloop.poll().await.map_err(|e| e.to_string())? {
+                v5::Event::Incoming(PacketV5::ConnAck(ack)) => Ok(MqttEvent::ConnAck {
+                    session_present: ack.session_present,
+                }),
+                v5::Event::Incoming(PacketV5::Publish(packet)) => Ok(MqttEvent::Publish(IncomingPublish {
+                    topic: String::from_utf8_lossy(&packet.topic).into_owned(),
+                    payload: packet.payload,
+                    qos: packet.qos as u8,
+                    retain: packet.retain,
+                })),
+                _ => Ok(MqttEvent::Other),
+            },
+        }
+    }
+}
+
+/// Vytvoří klienta podle konfigurace: verze protokolu,
+/// přihlášení, TLS a limit velikosti paketu jsou společné
+/// pro všechny klienty dashboardu.
+///
+/// Připojení proběhne až při prvním 'poll()'.
+pub fn connect(config: &MqttConfig, session: SessionOptions) -> (MqttClient, MqttEventLoop) {
+    // Konfigurace je už zvalidovaná, chyba by tu znamenala
+    // (nepravděpodobné) smazání souboru mezi startem a připojením
+    let transport = load_tls(&config.tls)
+        .expect("TLS konfigurace MQTT má být zvalidovaná")
+        .map(TlsFiles::into_transport);
+
+    match config.protocol {
+        MqttProtocol::V311 => {
+            let mut options = options_v311(config, &session);
+            if let Some(transport) = transport {
+                options.set_transport(transport);
+            }
+            let (client, eventloop) = rumqttc::AsyncClient::new(options, session.capacity);
+            (MqttClient::V311(client), MqttEventLoop::V311(eventloop))
+        }
+        MqttProtocol::V5 => {
+            let mut options = options_v5(config, &session);
+            if let Some(transport) = transport {
+                options.set_transport(transport);
+            }
+            let (client, eventloop) = v5::AsyncClient::new(options, session.capacity);
+            (MqttClient::V5(client), MqttEventLoop::V5(eventloop))
+        }
+    }
+}
+
+fn credentials(config: &MqttConfig) -> Option<(String, String)> {
+    config
+        .username
+        .as_ref()
+        .map(|user| (user.clone(), config.password.clone().unwrap_or_default()))
+}
+
+/// Nastavení pro MQTT 3.1.1 (bez TLS, to přidá 'connect')
+fn options_v311(config: &MqttConfig, session: &SessionOptions) -> rumqttc::MqttOptions {
+    let mut options = rumqttc::MqttOptions::new(session.client_id.clone(), config.host.clone(), config.port);
+    options
+        .set_keep_alive(session.keep_alive)
+        .set_clean_session(session.clean_session)
+        .set_max_packet_size(config.max_packet_size, config.max_packet_size);
+    if let Some((user, password)) = credentials(config) {
+        options.set_credentials(user, password);
+    }
+    options
+}
+
+/// Nastavení pro MQTT 5 (bez TLS, to přidá 'connect')
+fn options_v5(config: &MqttConfig, session: &SessionOptions) -> v5::MqttOptions {
+    let mut options = v5::MqttOptions::new(session.client_id.clone(), config.host.clone(), config.port);
+    options
+        .set_keep_alive(session.keep_alive)
+        .set_clean_start(session.clean_session);
+    // V MQTT 5 session bez 'session_expiry_interval' zaniká
+    // s odpojením - trvalou session si musíme vyžádat
+    if !session.clean_session {
+        let mut properties = ConnectProperties::new();
+        properties.session_expiry_interval = Some(u32::MAX);
+        options.set_connect_properties(properties);
+    }
+    // V MQTT 5 limit oznámíme brokeru v CONNECT - větší zprávy
+    // nám pak vůbec nepošle. Limit pro odeslání určí broker v CONNACK.
+    options.set_max_packet_size(Some(config.max_packet_size as u32));
+    if let Some((user, password)) = credentials(config) {
+        options.set_credentials(user, password);
+    }
+    options
+}
+
+/// Načte certifikáty pro TLS. 'Ok(None)' = TLS je vypnuté.
+///
+/// Volá se i při validaci konfigurace, aby chybějící nebo
+/// neplatný soubor shodil start, ne až připojení.
+pub fn load_tls(tls: &MqttTlsConfig) -> Result<Option<TlsFiles>, String> {
+    if !tls.enabled {
+        if tls.ca_file.is_some() || tls.client_cert_file.is_some() || tls.client_key_file.is_some() {
+            return Err("certifikáty jsou nastavené, ale 'enabled = false'".to_string());
+        }
+        return Ok(None);
+    }
+
+    let client_auth = match (&tls.client_cert_file, &tls.client_key_file) {
+        (Some(cert), Some(key)) => Some((read_pem(cert)?, read_pem(key)?)),
+        (None, None) => None,
+        _ => return Err("client_cert_file a client_key_file musí být nastavené oba".to_string()),
+    };
+    // 'rumqttc' neumí systémové certifikáty a klientský
+    // certifikát v jedné volbě, pro mTLS je proto CA povinná
+    if client_auth.is_some() && tls.ca_file.is_none() {
+        return Err("pro klientský certifikát je potřeba i ca_file".to_string());
+    }
+
+    Ok(Some(TlsFiles {
+        ca: tls.ca_file.as_deref().map(read_pem).transpose()?,
+        client_auth,
+    }))
+}
+
+/// Obsah certifikátů (PEM) načtených podle 'MqttTlsConfig'
+pub struct TlsFiles {
+    // 'None' = systémové certifikáty
+    ca: Option<Vec<u8>>,
+    client_auth: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl TlsFiles {
+    fn into_transport(self) -> Transport {
+        let tls = match self.ca {
+            Some(ca) => TlsConfiguration::Simple {
+                ca,
+                alpn: None,
+                client_auth: self.client_auth,
+            },
+            None => TlsConfiguration::default(),
+        };
+        Transport::Tls(tls)
+    }
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>, String> {
+    let content = std::fs::read(path)
+        .map_err(|e| format!("nelze načíst '{}': {}", path.display(), e))?;
+    if !content.windows(11).any(|w| w == b"-----BEGIN ") {
+        return Err(format!("'{}' není soubor ve formátu PEM", path.display()));
+    }
+    Ok(content)
+}
+
+fn qos_v311(qos: u8) -> rumqttc::QoS {
+    match qos {
+        0 => rumqttc::QoS::AtMostOnce,
+        1 => rumqttc::QoS::AtLeastOnce,
+        _ => rumqttc::QoS::ExactlyOnce,
+    }
+}
+
+fn qos_v5(qos: u8) -> v5::mqttbytes::QoS {
+    match qos {
+        0 => v5::mqttbytes::QoS::AtMostOnce,
+        1 => v5::mqttbytes::QoS::AtLeastOnce,
+        _ => v5::mqttbytes::QoS::ExactlyOnce,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::mqtt_topics::MAX_LIVE_PAYLOAD;
+
+    /// Trvalá session jako u ingestu - zpráva nad limitem by se
+    /// po každém připojení doručovala znovu
+    fn persistent_session() -> SessionOptions {
+        SessionOptions {
+            client_id: "dashboard-ingest".to_string(),
+            keep_alive: Duration::from_secs(30),
+            clean_session: false,
+            capacity: 10,
+        }
+    }
+
+    #[test]
+    fn default_packet_limit_fits_live_payload() {
+        let config = MqttConfig::default();
+        assert!(config.max_packet_size > MAX_LIVE_PAYLOAD);
+
+        let v311 = options_v311(&config, &persistent_session());
+        assert_eq!(v311.max_packet_size(), config.max_packet_size);
+        assert!(!v311.clean_session());
+    }
+
+    #[test]
+    fn v5_persistent_session_keeps_packet_limit_and_expiry() {
+        let config = MqttConfig { max_packet_size: 1024 * 1024, ..MqttConfig::default() };
+        let options = options_v5(&config, &persistent_session());
+
+        assert_eq!(options.max_packet_size(), Some(1024 * 1024));
+        let properties = options.connect_properties().unwrap();
+        assert_eq!(properties.session_expiry_interval, Some(u32::MAX));
+        assert!(!options.clean_start());
+    }
+}
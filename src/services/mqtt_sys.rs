@@ -0,0 +1,335 @@
+use crate::models::{MqttConnection, MqttLoadAverage, MqttRates, MqttStats, MqttStatsSample};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, RwLock};
+
+/// Filtr pro odběr celého $SYS stromu brokeru
+pub const SYS_FILTER: &str = "$SYS/broker/#";
+
+/// Čítač, ze kterého počítáme rychlost za sekundu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RateCounter {
+    MessagesReceived,
+    MessagesSent,
+    BytesReceived,
+    BytesSent,
+    PublishDropped,
+}
+
+/// Pamatuje si předchozí vzorek každého čítače.
+///
+/// Mosquitto posílá $SYS hodnoty každých 'sys_interval' sekund
+/// (výchozí 10 s), ale jen ty, které se změnily. Rychlost je tedy
+/// průměr od předchozí změny čítače.
+///
+/// Když provoz ustane, broker čítač přestane posílat. Proto
+/// 'expire' rychlost vynuluje, jakmile čítač nepřišel déle než
+/// dva intervaly brokeru.
+#[derive(Debug, Default)]
+pub struct RateTracker {
+    previous: HashMap<RateCounter, (u64, Instant)>,
+    // Nejkratší mezera mezi dvěma vzorky čítače = odhad 'sys_interval'
+    interval: Option<Duration>,
+}
+
+impl RateTracker {
+    /// Uloží nový vzorek a vrátí rychlost od předchozího.
+    ///
+    /// Pokud čítač klesl (restart brokeru), začínáme znovu.
+    fn sample(&mut self, counter: RateCounter, value: u64, now: Instant) -> Option<f64> {
+        let rate = match self.previous.get(&counter) {
+            Some(&(old, at)) if value >= old => {
+                let elapsed = now.duration_since(at);
+                if !elapsed.is_zero() {
+                    self.interval = Some(self.interval.map_or(elapsed, |i| i.min(elapsed)));
+                }
+                let elapsed = elapsed.as_secs_f64();
+                (elapsed > 0.0).then(|| (value - old) as f64 / elapsed)
+            }
+            _ => None,
+        };
+        self.previous.insert(counter, (value, now));
+        rate
+    }
+
+    /// Vynuluje rychlosti čítačů, které broker déle než dva intervaly
+    /// neposlal - čítač se od posledního vzorku nezměnil.
+    ///
+    /// Vrací 'true', pokud se některá rychlost změnila.
+    pub fn expire(&self, rates: &mut MqttRates, now: Instant) -> bool {
+        let Some(interval) = self.interval else {
+            return false;
+        };
+        let mut changed = false;
+        for (counter, &(_, at)) in &self.previous {
+            let rate = rate_mut(rates, *counter);
+            if now.duration_since(at) > interval * 2 && *rate != Some(0.0) {
+                *rate = Some(0.0);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+fn rate_mut(rates: &mut MqttRates, counter: RateCounter) -> &mut Option<f64> {
+    match counter {
+        RateCounter::MessagesReceived => &mut rates.messages_received,
+        RateCounter::MessagesSent => &mut rates.messages_sent,
+        RateCounter::BytesReceived => &mut rates.bytes_received,
+        RateCounter::BytesSent => &mut rates.bytes_sent,
+        RateCounter::PublishDropped => &mut rates.publish_dropped,
+    }
+}
+
+/// Zapracuje jednu $SYS zprávu do typovaných statistik.
+///
+/// Vrací 'false' pro téma, které neznáme (statistiky se nezmění).
+pub fn apply(stats: &mut MqttStats, rates: &mut RateTracker, topic: &str, payload: &str) -> bool {
+    let Some(path) = topic.strip_prefix("$SYS/broker/") else {
+        return false;
+    };
+    let payload = payload.trim();
+
+    if let Some(load) = path.strip_prefix("load/") {
+        return apply_load(stats, load, payload);
+    }
+
+    let counter = |value: &mut Option<u64>| *value = parse_u64(payload);
+    match path {
+        "version" => stats.version = Some(payload.to_string()),
+        // Mosquitto posílá "1234 seconds"
+        "uptime" => counter(&mut stats.uptime_seconds),
+
+        "clients/total" => counter(&mut stats.clients.total),
+        // 'active' je starší název pro 'connected'
+        "clients/connected" | "clients/active" => counter(&mut stats.clients.connected),
+        "clients/disconnected" | "clients/inactive" => counter(&mut stats.clients.disconnected),
+        "clients/expired" => counter(&mut stats.clients.expired),
+        "clients/maximum" => counter(&mut stats.clients.maximum),
+
+        "messages/received" => {
+            counter(&mut stats.messages.received);
+            stats.rates.messages_received = sample(rates, RateCounter::MessagesReceived, stats.messages.received);
+        }
+        "messages/sent" => {
+            counter(&mut stats.messages.sent);
+            stats.rates.messages_sent = sample(rates, RateCounter::MessagesSent, stats.messages.sent);
+        }
+        "messages/stored" | "store/messages/count" => counter(&mut stats.stored),
+
+        "bytes/received" => {
+            counter(&mut stats.bytes.received);
+            stats.rates.bytes_received = sample(rates, RateCounter::BytesReceived, stats.bytes.received);
+        }
+        "bytes/sent" => {
+            counter(&mut stats.bytes.sent);
+            stats.rates.bytes_sent = sample(rates, RateCounter::BytesSent, stats.bytes.sent);
+        }
+
+        "publish/messages/received" => counter(&mut stats.publish.received),
+        "publish/messages/sent" => counter(&mut stats.publish.sent),
+        "publish/messages/dropped" => {
+            counter(&mut stats.publish.dropped);
+            stats.rates.publish_dropped = sample(rates, RateCounter::PublishDropped, stats.publish.dropped);
+        }
+
+        "subscriptions/count" => counter(&mut stats.subscriptions),
+        "retained messages/count" => counter(&mut stats.retained),
+        "heap/current" | "heap/current size" => counter(&mut stats.heap.current),
+        "heap/maximum" | "heap/maximum size" => counter(&mut stats.heap.maximum),
+
+        _ => return false,
+    }
+
+    stats.updated_at = Some(Utc::now());
+    true
+}
+
+/// '$SYS/broker/load/<co>/<1min|5min|15min>'
+fn apply_load(stats: &mut MqttStats, path: &str, payload: &str) -> bool {
+    let Some((name, window)) = path.rsplit_once('/') else {
+        return false;
+    };
+    let load = &mut stats.load;
+    let average: &mut MqttLoadAverage = match name {
+        "messages/received" => &mut load.messages_received,
+        "messages/sent" => &mut load.messages_sent,
+        "bytes/received" => &mut load.bytes_received,
+        "bytes/sent" => &mut load.bytes_sent,
+        "publish/received" => &mut load.publish_received,
+        "publish/sent" => &mut load.publish_sent,
+        "publish/dropped" => &mut load.publish_dropped,
+        "connections" => &mut load.connections,
+        "sockets" => &mut load.sockets,
+        _ => return false,
+    };
+    let slot = match window {
+        "1min" => &mut average.min1,
+        "5min" => &mut average.min5,
+        "15min" => &mut average.min15,
+        _ => return false,
+    };
+    *slot = payload.parse::<f64>().ok();
+
+    stats.updated_at = Some(Utc::now());
+    true
+}
+
+fn sample(rates: &mut RateTracker, counter: RateCounter, value: Option<u64>) -> Option<f64> {
+    value.and_then(|value| rates.sample(counter, value, Instant::now()))
+}
+
+/// Celé číslo na začátku payloadu ("1234 seconds" -> 1234)
+fn parse_u64(payload: &str) -> Option<u64> {
+    payload.split_whitespace().next()?.parse().ok()
+}
+
+/// Kruhový buffer vzorků statistik s pevnou kapacitou.
+///
+/// Nejstarší vzorek se při zaplnění zahodí, paměť je tedy
+/// omezená: 'history_minutes * 60 / history_interval_secs' vzorků.
+#[derive(Debug)]
+pub struct StatsHistory {
+    samples: VecDeque<MqttStatsSample>,
+    capacity: usize,
+    interval: Duration,
+}
+
+impl StatsHistory {
+    pub fn new(history: Duration, interval: Duration) -> Self {
+        let capacity = (history.as_secs() / interval.as_secs().max(1)).max(1) as usize;
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            interval,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn push(&mut self, sample: MqttStatsSample) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Vzorky od času 'since' (nebo všechny), od nejstaršího
+    pub fn since(&self, since: Option<DateTime<Utc>>) -> Vec<MqttStatsSample> {
+        self.samples
+            .iter()
+            .filter(|s| since.is_none_or(|since| s.time >= since))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Spustí úlohu, která každých 'interval' uloží vzorek statistik do historie.
+///
+/// Když sběrač nemá spojení s brokerem, vzorek se nezapíše -
+/// v grafu pak zůstane mezera místo "zamrzlých" hodnot.
+pub fn launch_history_sampler(
+    stats: watch::Receiver<MqttStats>,
+    connection: Arc<RwLock<MqttConnection>>,
+    history: Arc<RwLock<StatsHistory>>,
+) {
+    tokio::spawn(async move {
+        let interval = history.read().await.interval();
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if !connection.read().await.status.connected {
+                continue;
+            }
+            let sample = MqttStatsSample::from_stats(&stats.borrow(), Utc::now());
+            history.write().await.push(sample);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_u64_takes_leading_number() {
+        assert_eq!(parse_u64("1234"), Some(1234));
+        assert_eq!(parse_u64("1234 seconds"), Some(1234));
+        assert_eq!(parse_u64(" 7\n"), Some(7));
+        assert_eq!(parse_u64(""), None);
+        assert_eq!(parse_u64("-1"), None);
+        assert_eq!(parse_u64("mosquitto 2.0"), None);
+    }
+
+    #[test]
+    fn apply_fills_typed_stats() {
+        let mut stats = MqttStats::default();
+        let mut rates = RateTracker::default();
+
+        assert!(apply(&mut stats, &mut rates, "$SYS/broker/version", "mosquitto version 2.0.18"));
+        assert!(apply(&mut stats, &mut rates, "$SYS/broker/uptime", "3600 seconds"));
+        assert!(apply(&mut stats, &mut rates, "$SYS/broker/clients/active", "3"));
+        assert!(apply(&mut stats, &mut rates, "$SYS/broker/messages/received", "100"));
+        assert!(apply(&mut stats, &mut rates, "$SYS/broker/retained messages/count", "12"));
+        assert!(apply(&mut stats, &mut rates, "$SYS/broker/load/messages/received/1min", "4.5"));
+
+        assert_eq!(stats.version.as_deref(), Some("mosquitto version 2.0.18"));
+        assert_eq!(stats.uptime_seconds, Some(3600));
+        assert_eq!(stats.clients.connected, Some(3));
+        assert_eq!(stats.messages.received, Some(100));
+        assert_eq!(stats.retained, Some(12));
+        assert_eq!(stats.load.messages_received.min1, Some(4.5));
+        // První vzorek čítače rychlost ještě nemá
+        assert_eq!(stats.rates.messages_received, None);
+        assert!(stats.updated_at.is_some());
+    }
+
+    #[test]
+    fn apply_ignores_unknown_topics() {
+        let mut stats = MqttStats::default();
+        let mut rates = RateTracker::default();
+        assert!(!apply(&mut stats, &mut rates, "$SYS/broker/neznamy", "1"));
+        assert!(!apply(&mut stats, &mut rates, "$SYS/broker/load/messages/received/2min", "1"));
+        assert!(!apply(&mut stats, &mut rates, "sensors/kuchyn/temp", "21.5"));
+        assert!(stats.updated_at.is_none());
+    }
+
+    #[test]
+    fn rate_from_consecutive_samples() {
+        let mut rates = RateTracker::default();
+        let t0 = Instant::now();
+        assert_eq!(rates.sample(RateCounter::MessagesReceived, 100, t0), None);
+        let rate = rates.sample(RateCounter::MessagesReceived, 150, t0 + Duration::from_secs(10));
+        assert_eq!(rate, Some(5.0));
+        // Čítač klesl - broker se restartoval
+        assert_eq!(rates.sample(RateCounter::MessagesReceived, 3, t0 + Duration::from_secs(20)), None);
+    }
+
+    #[test]
+    fn rate_expires_when_counter_stops() {
+        let mut tracker = RateTracker::default();
+        let mut rates = MqttRates::default();
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+
+        // Bez odhadu intervalu nic nevynulujeme
+        tracker.sample(RateCounter::MessagesSent, 10, at(0));
+        assert!(!tracker.expire(&mut rates, at(100)));
+
+        rates.messages_sent = tracker.sample(RateCounter::MessagesSent, 110, at(10));
+        assert_eq!(rates.messages_sent, Some(10.0));
+        // Do dvou intervalů (20 s) rychlost platí
+        assert!(!tracker.expire(&mut rates, at(30)));
+        assert_eq!(rates.messages_sent, Some(10.0));
+
+        assert!(tracker.expire(&mut rates, at(31)));
+        assert_eq!(rates.messages_sent, Some(0.0));
+        // Podruhé už se nic nemění
+        assert!(!tracker.expire(&mut rates, at(40)));
+    }
+}
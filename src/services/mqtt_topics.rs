@@ -0,0 +1,389 @@
+use crate::models::{MqttTopicMessage, MqttTopicRow};
+use crate::services::mqtt_decode::DecoderRegistry;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// Kolik bajtů z payloadu si u každého tématu pamatujeme.
+/// Delší zprávy se ořízne, původní velikost zůstane v 'payload_size'.
+/// Strom drží až 'max_topics' zpráv najednou (výchozí 10 000 = max.
+/// ~40 MB), celý payload je k vidění v živém výpisu.
+pub const MAX_STORED_PAYLOAD: usize = 4 * 1024;
+
+/// Kolik bajtů payloadu dekódujeme pro živý výpis. Zpráva se
+/// nikam neukládá, limit jen brání dekódování obřích payloadů.
+pub const MAX_LIVE_PAYLOAD: usize = 64 * 1024;
+
+/// Poslední zpráva přijatá na jednom tématu
+#[derive(Debug, Clone)]
+pub struct TopicMessage {
+    pub payload: Vec<u8>,
+    pub payload_size: usize,
+    pub received_at: DateTime<Utc>,
+    pub qos: u8,
+    pub retain: bool,
+    // Kolik zpráv na tématu přišlo od startu dashboardu
+    pub count: u64,
+}
+
+/// Jeden uzel stromu - úroveň tématu mezi lomítky
+#[derive(Debug, Default)]
+struct TopicNode {
+    children: BTreeMap<String, TopicNode>,
+    message: Option<TopicMessage>,
+}
+
+impl TopicNode {
+    /// Počet zpráv v celém podstromu (včetně tohoto uzlu)
+    fn subtree_messages(&self) -> u64 {
+        self.message.as_ref().map_or(0, |m| m.count)
+            + self.children.values().map(TopicNode::subtree_messages).sum::<u64>()
+    }
+}
+
+/// Strom MQTT témat s poslední zprávou pro každé téma.
+///
+/// Podobné výpisu z 'mosquitto_sub -v -t "#"', jen si pamatuje
+/// poslední stav. Počet témat je omezený, aby nás zařízení
+/// posílající unikátní témata nepřipravilo o paměť.
+#[derive(Debug)]
+pub struct TopicTree {
+    root: TopicNode,
+    max_topics: usize,
+    topic_count: usize,
+    dropped: u64,
+}
+
+impl TopicTree {
+    pub fn new(max_topics: usize) -> Self {
+        Self {
+            root: TopicNode::default(),
+            max_topics,
+            topic_count: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Počet témat, na kterých přišla aspoň jedna zpráva
+    pub fn topic_count(&self) -> usize {
+        self.topic_count
+    }
+
+    /// Počet zpráv zahozených kvůli limitu 'max_topics'
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Uloží přijatou zprávu do stromu
+    pub fn insert(&mut self, topic: &str, payload: &[u8], qos: u8, retain: bool) {
+        if self.find(topic).and_then(|n| n.message.as_ref()).is_none()
+            && self.topic_count >= self.max_topics
+        {
+            self.dropped += 1;
+            return;
+        }
+
+        let mut node = &mut self.root;
+        for level in topic.split('/') {
+            node = node.children.entry(level.to_string()).or_default();
+        }
+
+        let count = match &node.message {
+            Some(previous) => previous.count + 1,
+            None => {
+                self.topic_count += 1;
+                1
+            }
+        };
+        node.message = Some(TopicMessage {
+            payload: payload[..payload.len().min(MAX_STORED_PAYLOAD)].to_vec(),
+            payload_size: payload.len(),
+            received_at: Utc::now(),
+            qos,
+            retain,
+            count,
+        });
+    }
+
+    /// Poslední zpráva na konkrétním tématu
+    pub fn get(&self, topic: &str) -> Option<&TopicMessage> {
+        self.find(topic).and_then(|n| n.message.as_ref())
+    }
+
+    /// Existuje v stromu uzel pro dané téma (i bez vlastní zprávy)?
+    pub fn contains(&self, topic: &str) -> bool {
+        topic.is_empty() || self.find(topic).is_some()
+    }
+
+    /// Vrátí podstrom pod 'prefix' jako ploché řádky pro šablonu
+    /// (do hloubky, seřazené podle názvu). Prázdný 'prefix' je celý strom.
+    ///
+    /// Vrací nejvýš 'limit' řádků a příznak, jestli se něco nevešlo.
+    /// Náhledy payloadů dekóduje 'decoders'.
+    pub fn rows(&self, prefix: &str, limit: usize, decoders: &DecoderRegistry) -> (Vec<MqttTopicRow>, bool) {
+        let mut rows = Vec::new();
+        let Some(start) = (if prefix.is_empty() { Some(&self.root) } else { self.find(prefix) }) else {
+            return (rows, false);
+        };
+        let truncated = !collect_rows(start, prefix, 0, limit, decoders, &mut rows);
+        (rows, truncated)
+    }
+
+    fn find(&self, topic: &str) -> Option<&TopicNode> {
+        topic
+            .split('/')
+            .try_fold(&self.root, |node, level| node.children.get(level))
+    }
+}
+
+/// Projde děti uzlu do hloubky. Vrací 'false', pokud se narazilo na limit.
+fn collect_rows(
+    node: &TopicNode,
+    path: &str,
+    depth: usize,
+    limit: usize,
+    decoders: &DecoderRegistry,
+    rows: &mut Vec<MqttTopicRow>,
+) -> bool {
+    for (name, child) in &node.children {
+        if rows.len() >= limit {
+            return false;
+        }
+        // Na nejvyšší úrovni stromu nemá téma žádný prefix
+        let topic = if depth == 0 && path.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", path, name)
+        };
+        rows.push(MqttTopicRow {
+            topic: topic.clone(),
+            name: name.clone(),
+            depth,
+            children: child.children.len(),
+            subtree_messages: child.subtree_messages(),
+            message: child.message.as_ref().map(|m| message_view(&topic, m, PREVIEW_CHARS, decoders)),
+        });
+        if !collect_rows(child, &topic, depth + 1, limit, decoders, rows) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Kolik znaků payloadu ukazujeme v přehledu stromu
+const PREVIEW_CHARS: usize = 80;
+
+/// Převede uloženou zprávu na View Model. Payload dekóduje
+/// dekodér nastavený pro téma, text se zkrátí na 'max_chars' znaků.
+pub fn message_view(
+    topic: &str,
+    message: &TopicMessage,
+    max_chars: usize,
+    decoders: &DecoderRegistry,
+) -> MqttTopicMessage {
+    let decoded = decoders.decode(topic, &message.payload);
+    MqttTopicMessage {
+        payload: preview(&decoded.text, max_chars, message.payload.len() < message.payload_size),
+        decoder: decoded.decoder.to_string(),
+        decode_error: decoded.error,
+        payload_size: message.payload_size,
+        received_at: message.received_at,
+        qos: message.qos,
+        retain: message.retain,
+        count: message.count,
+    }
+}
+
+/// Text zkrácený na 'max_chars' znaků. Zkrácený text (nebo text
+/// z už oříznutého payloadu - 'cut') končí '…'.
+pub fn preview(text: &str, max_chars: usize, cut: bool) -> String {
+    let mut preview: String = text.chars().take(max_chars).collect();
+    if cut || preview.len() < text.len() {
+        preview.push('…');
+    }
+    preview
+}
+
+/// Zkontrolovaný filtr odběru, proti kterému lze porovnávat témata.
+///
+/// Porovnání odpovídá brokeru: '+' zastoupí právě jednu úroveň,
+/// '#' zbytek tématu (i žádnou úroveň, 'a/#' tedy odpovídá i 'a')
+/// a témata začínající '$' nezachytí zástupný znak na první úrovni.
+#[derive(Debug, Clone)]
+pub struct TopicFilter {
+    filter: String,
+    levels: Vec<String>,
+}
+
+impl TopicFilter {
+    pub fn parse(filter: &str) -> Result<Self, String> {
+        validate_filter(filter)?;
+        Ok(Self {
+            filter: filter.to_string(),
+            levels: filter.split('/').map(str::to_string).collect(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.filter
+    }
+
+    /// Počet zástupných úrovní ('+' a '#') ve filtru
+    pub fn wildcards(&self) -> usize {
+        self.levels.iter().filter(|l| *l == "+" || *l == "#").count()
+    }
+
+    pub fn matches(&self, topic: &str) -> bool {
+        self.captures(topic).is_some()
+    }
+
+    /// Porovná téma s filtrem. Při shodě vrátí úrovně zachycené
+    /// zástupnými znaky ('+' jednu úroveň, '#' celý zbytek tématu).
+    pub fn captures(&self, topic: &str) -> Option<Vec<String>> {
+        let levels: Vec<&str> = topic.split('/').collect();
+        if topic.starts_with('$') && self.levels.first().is_some_and(|f| f == "+" || f == "#") {
+            return None;
+        }
+
+        let mut captures = Vec::new();
+        for (index, level) in self.levels.iter().enumerate() {
+            match level.as_str() {
+                "#" => {
+                    captures.push(levels.get(index..).map(|rest| rest.join("/")).unwrap_or_default());
+                    return Some(captures);
+                }
+                "+" => captures.push(levels.get(index)?.to_string()),
+                exact => {
+                    if levels.get(index) != Some(&exact) {
+                        return None;
+                    }
+                }
+            }
+        }
+        (levels.len() == self.levels.len()).then_some(captures)
+    }
+}
+
+/// Zkontroluje filtr pro odběr ('sensors/+/temp', '#'...) podle MQTT specifikace.
+///
+/// '#' smí být jen jako celá poslední úroveň, '+' jen jako celá úroveň.
+pub fn validate_filter(filter: &str) -> Result<(), String> {
+    if filter.is_empty() {
+        return Err("filtr nesmí být prázdný".to_string());
+    }
+    let levels: Vec<&str> = filter.split('/').collect();
+    for (index, level) in levels.iter().enumerate() {
+        if level.contains('#') && (*level != "#" || index != levels.len() - 1) {
+            return Err(format!("'{}': '#' musí být samostatně na poslední úrovni", filter));
+        }
+        if level.contains('+') && *level != "+" {
+            return Err(format!("'{}': '+' musí tvořit celou úroveň", filter));
+        }
+    }
+    Ok(())
+}
+
+/// Zkontroluje název tématu pro publikování - na rozdíl od filtru
+/// nesmí obsahovat zástupné znaky '+' a '#'.
+pub fn validate_topic_name(topic: &str) -> Result<(), String> {
+    if topic.is_empty() {
+        return Err("téma nesmí být prázdné".to_string());
+    }
+    if topic.contains(['+', '#']) {
+        return Err(format!("'{}': téma pro publikování nesmí obsahovat '+' ani '#'", topic));
+    }
+    if topic.len() > u16::MAX as usize {
+        return Err("téma je delší než 65535 bajtů".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(filter: &str) -> TopicFilter {
+        TopicFilter::parse(filter).unwrap()
+    }
+
+    #[test]
+    fn exact_filter_matches_only_same_topic() {
+        let f = filter("sensors/kuchyn/temp");
+        assert!(f.matches("sensors/kuchyn/temp"));
+        assert!(!f.matches("sensors/kuchyn"));
+        assert!(!f.matches("sensors/kuchyn/temp/raw"));
+        assert!(!f.matches("sensors/obyvak/temp"));
+        assert_eq!(f.wildcards(), 0);
+    }
+
+    #[test]
+    fn plus_matches_exactly_one_level() {
+        let f = filter("sensors/+/temp");
+        assert_eq!(f.captures("sensors/kuchyn/temp"), Some(vec!["kuchyn".to_string()]));
+        assert_eq!(f.captures("sensors//temp"), Some(vec![String::new()]));
+        assert!(!f.matches("sensors/temp"));
+        assert!(!f.matches("sensors/a/b/temp"));
+        assert_eq!(f.wildcards(), 1);
+    }
+
+    #[test]
+    fn hash_matches_rest_including_parent() {
+        let f = filter("sensors/#");
+        assert_eq!(f.captures("sensors/kuchyn/temp"), Some(vec!["kuchyn/temp".to_string()]));
+        assert_eq!(f.captures("sensors"), Some(vec![String::new()]));
+        assert!(!f.matches("other/kuchyn"));
+
+        let all = filter("#");
+        assert!(all.matches("a"));
+        assert!(all.matches("a/b/c"));
+    }
+
+    #[test]
+    fn wildcard_on_first_level_skips_dollar_topics() {
+        assert!(!filter("#").matches("$SYS/broker/uptime"));
+        assert!(!filter("+/broker/uptime").matches("$SYS/broker/uptime"));
+        assert!(filter("$SYS/#").matches("$SYS/broker/uptime"));
+        assert!(filter("$SYS/+/uptime").matches("$SYS/broker/uptime"));
+    }
+
+    #[test]
+    fn invalid_filters_are_rejected() {
+        for invalid in ["", "a/#/b", "a#", "a/b#", "a+", "a/+b/c"] {
+            assert!(validate_filter(invalid).is_err(), "'{}' měl být odmítnut", invalid);
+            assert!(TopicFilter::parse(invalid).is_err());
+        }
+        for valid in ["#", "+", "a/+/#", "/a", "a//b", "+/+"] {
+            assert!(validate_filter(valid).is_ok(), "'{}' měl projít", valid);
+        }
+    }
+
+    #[test]
+    fn topic_name_without_wildcards() {
+        assert!(validate_topic_name("sensors/kuchyn/temp").is_ok());
+        assert!(validate_topic_name("").is_err());
+        assert!(validate_topic_name("sensors/+/temp").is_err());
+        assert!(validate_topic_name("sensors/#").is_err());
+        assert!(validate_topic_name(&"a".repeat(u16::MAX as usize + 1)).is_err());
+    }
+
+    #[test]
+    fn tree_caps_payload_and_topic_count() {
+        let mut tree = TopicTree::new(2);
+        let payload = vec![b'x'; MAX_STORED_PAYLOAD + 10];
+        tree.insert("a/b", &payload, 0, false);
+        tree.insert("a/b", b"kratka", 1, true);
+        tree.insert("a/c", &payload, 0, false);
+        tree.insert("a/d", b"navic", 0, false);
+
+        let message = tree.get("a/b").unwrap();
+        assert_eq!(message.payload, b"kratka");
+        assert_eq!(message.count, 2);
+        let message = tree.get("a/c").unwrap();
+        assert_eq!(message.payload.len(), MAX_STORED_PAYLOAD);
+        assert_eq!(message.payload_size, MAX_STORED_PAYLOAD + 10);
+
+        assert_eq!(tree.topic_count(), 2);
+        assert_eq!(tree.dropped(), 1);
+        assert!(tree.get("a/d").is_none());
+        assert!(tree.contains("a"));
+    }
+}
@@ -1,27 +1,267 @@
 /// Provede fulltextové vyhledávání v Manticore
-use crate::models::ManticoreResponse;
+use crate::config::{FacetConfig, FacetKind};
+use crate::models::{
+    HighlightPart, ManticoreBucket, ManticoreColumn, ManticoreHit, ManticoreResponse, ManticoreSqlResult,
+    ManticoreTableInfo, SearchResult, SearchSnippet,
+};
+use crate::services::manticore_query::{BoolQuery, Query, RangeQuery};
+use chrono::DateTime;
 use reqwest::Client as ManticoreClient;
-use serde_json::json;
+use serde_json::{json, Value};
 
 // PŘIDÁNO: Potřebujeme serde_json pro ruční parsování
 use serde_json::from_str as parse_json_from_string;
 //use std::env;
 use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Manticore ve výchozím nastavení ('max_matches') vrátí nejvýš
+/// prvních 1000 shod - dál stránkovat nejde.
+pub const MAX_MATCHES: u32 = 1000;
+
+/// Značky, kterými Manticore obalí shody v úryvcích. Úryvky jsou
+/// surový text dokumentu (bez escapování), proto značky nejsou HTML:
+/// text rozdělíme na kusy a '<mark>' přidá až šablona.
+const MARK_START: char = '\u{2}';
+const MARK_END: char = '\u{3}';
+
+/// Řazení výsledků podle jednoho pole ('id', '-cena', '_score'...)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchSort {
+    pub field: String,
+    pub descending: bool,
+}
+
+impl FromStr for SearchSort {
+    type Err = String;
+
+    /// 'pole' = vzestupně, '-pole' = sestupně
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (field, descending) = match value.strip_prefix('-') {
+            Some(field) => (field, true),
+            None => (value, false),
+        };
+        validate_field(field).map_err(|e| format!("Neplatné řazení '{}': {}", value, e))?;
+        Ok(Self { field: field.to_string(), descending })
+    }
+}
+
+/// Ověří název pole/atributu (písmena, číslice, '_')
+pub fn validate_field(field: &str) -> Result<(), String> {
+    if field.is_empty() || !field.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("neplatný název pole '{}'", field));
+    }
+    Ok(())
+}
+
+/// Délka dne pro facet typu 'date' (timestampy jsou v sekundách)
+const DAY_SECS: i64 = 86_400;
+
+/// Filtr výsledků podle atributu (viz 'filter_clauses')
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchFilter {
+    // Atribut má přesně tuto hodnotu (číslo nebo řetězec)
+    Equals { field: String, value: Value },
+    // Atribut je v intervalu '[gte, lt)'
+    Range { field: String, gte: i64, lt: i64 },
+}
+
+impl SearchFilter {
+    /// Filtr z parametru 'filter=pole:hodnota'.
+    ///
+    /// Pole musí být mezi 'facets'. U facetu 'date' je hodnota
+    /// začátek dne (unix timestamp), u ostatních JSON číslo nebo
+    /// řetězec - řetězec, který nevypadá jako JSON, nemusí mít uvozovky.
+    pub fn parse(param: &str, facets: &[&FacetConfig]) -> Result<Self, String> {
+        let (field, raw) = param
+            .split_once(':')
+            .ok_or_else(|| format!("filtr '{}' nemá tvar 'pole:hodnota'", param))?;
+        let facet = facets
+            .iter()
+            .find(|facet| facet.field == field)
+            .ok_or_else(|| format!("pole '{}' není facet", field))?;
+
+        match facet.kind {
+            FacetKind::Date => {
+                let (day, next_day) = raw
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|day| Some((day, day.checked_add(DAY_SECS)?)))
+                    .ok_or_else(|| format!("'{}' není timestamp začátku dne", raw))?;
+                Ok(Self::Range { field: field.to_string(), gte: day, lt: next_day })
+            }
+            FacetKind::Terms => {
+                let value = serde_json::from_str::<Value>(raw)
+                    .ok()
+                    .filter(|value| value.is_number() || value.is_string())
+                    .unwrap_or_else(|| Value::String(raw.to_string()));
+                Ok(Self::Equals { field: field.to_string(), value })
+            }
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        match self {
+            Self::Equals { field, .. } | Self::Range { field, .. } => field,
+        }
+    }
+
+    /// Hodnota parametru 'filter' (opak 'parse')
+    pub fn param(&self) -> String {
+        match self {
+            Self::Equals { field, value: Value::String(text) } => {
+                // Řetězec, který by se načetl jako číslo, musí mít uvozovky
+                let looks_like_json = serde_json::from_str::<Value>(text).is_ok_and(|v| !v.is_string());
+                if looks_like_json || text.starts_with('"') {
+                    format!("{}:{}", field, Value::String(text.clone()))
+                } else {
+                    format!("{}:{}", field, text)
+                }
+            }
+            Self::Equals { field, value } => format!("{}:{}", field, value),
+            Self::Range { field, gte, .. } => format!("{}:{}", field, gte),
+        }
+    }
+
+    /// Popisek hodnoty pro uživatele
+    pub fn label(&self) -> String {
+        match self {
+            Self::Equals { value: Value::String(text), .. } => text.clone(),
+            Self::Equals { value, .. } => value.to_string(),
+            Self::Range { gte, .. } => day_label(*gte),
+        }
+    }
+
+    fn to_query(&self) -> Query {
+        match self {
+            Self::Equals { field, value } => Query::equals(field, value.clone()),
+            Self::Range { field, gte, lt } => RangeQuery::new(field).gte(*gte).lt(*lt).into(),
+        }
+    }
+}
+
+/// Klauzule pro 'bool.must', jedna za každé pole. Filtry různých polí
+/// musí platit všechny, z více hodnot stejného pole (např. dva hosty
+/// zaškrtnuté ve facetu) stačí jedna ('should').
+pub fn filter_clauses(filters: &[SearchFilter]) -> Vec<Query> {
+    let mut fields: Vec<&str> = filters.iter().map(SearchFilter::field).collect();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .map(|field| {
+            let mut any_of: Vec<Query> = filters
+                .iter()
+                .filter(|filter| filter.field() == field)
+                .map(SearchFilter::to_query)
+                .collect();
+            if any_of.len() == 1 {
+                any_of.remove(0)
+            } else {
+                any_of.into_iter().fold(BoolQuery::new(), BoolQuery::should).into()
+            }
+        })
+        .collect()
+}
+
+/// Filtr, který by vytvořilo kliknutí na 'bucket' facetu
+pub fn bucket_filter(facet: &FacetConfig, bucket: &ManticoreBucket) -> Option<SearchFilter> {
+    let field = facet.field.clone();
+    match facet.kind {
+        FacetKind::Date => {
+            let gte = bucket.key.as_i64()?;
+            Some(SearchFilter::Range { field, gte, lt: gte.checked_add(DAY_SECS)? })
+        }
+        FacetKind::Terms => Some(SearchFilter::Equals { field, value: bucket.key.clone() }),
+    }
+}
+
+/// Datum (UTC) pro začátek dne v unix sekundách
+fn day_label(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.date_naive().to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+impl fmt::Display for SearchSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.descending {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", self.field)
+    }
+}
+
+/// Parametry jednoho vyhledávání
+#[derive(Debug, Clone)]
+pub struct SearchRequest {
+    pub index: String,
+    pub query: Query,
+    pub limit: u32,
+    pub offset: u32,
+    // 'None' = podle relevance ('_score' sestupně)
+    pub sort: Option<SearchSort>,
+    // Pole pro zvýraznění shod, prázdné = všechna fulltextová
+    pub highlight_fields: Vec<String>,
+    // Filtry z facetů (všechny musí platit)
+    pub filters: Vec<SearchFilter>,
+    // Facety, pro které chceme agregace (jen ty pro tento index)
+    pub facets: Vec<FacetConfig>,
+}
 
 /// Provede fulltextové vyhledávání v Manticore
 pub async fn search_manticore(
     client: &ManticoreClient,
     base_url: &str,
-    index_name: &str,
-    query: &str,
+    request: &SearchRequest,
 ) -> Result<ManticoreResponse, Box<dyn Error>> {
     
     let search_url = format!("{}/search", base_url);
 
-    let manticore_query = json!({
-        "index": index_name,
-        "query": { "match": { "_all": query } },
+    // Filtry z facetů se přidají k dotazu jako další 'must'.
+    // Dotaz z pokročilého hledání ('bool' bez 'should') jen rozšíříme
+    let mut query = match &request.query {
+        Query::Bool(query) if query.should.is_empty() => query.clone(),
+        other => BoolQuery::new().must(other.clone()),
+    };
+    for clause in filter_clauses(&request.filters) {
+        query = query.must(clause);
+    }
+    let query = query.build();
+
+    let mut manticore_query = json!({
+        "index": request.index,
+        "query": query.to_json(),
+        "limit": request.limit,
+        "offset": request.offset,
     });
+    if let Some(sort) = &request.sort {
+        let order = if sort.descending { "desc" } else { "asc" };
+        manticore_query["sort"] = json!([{ sort.field.as_str(): order }]);
+    }
+    let mut highlight = json!({
+        "pre_tags": MARK_START.to_string(),
+        "post_tags": MARK_END.to_string(),
+    });
+    if !request.highlight_fields.is_empty() {
+        highlight["fields"] = json!(request.highlight_fields);
+    }
+    manticore_query["highlight"] = highlight;
+    if !request.facets.is_empty() {
+        let aggs: serde_json::Map<String, Value> = request
+            .facets
+            .iter()
+            .map(|facet| {
+                let agg = match facet.kind {
+                    FacetKind::Terms => json!({ "terms": { "field": facet.field, "size": facet.size } }),
+                    FacetKind::Date => json!({ "histogram": { "field": facet.field, "interval": DAY_SECS } }),
+                };
+                (facet.field.clone(), agg)
+            })
+            .collect();
+        manticore_query["aggs"] = Value::Object(aggs);
+    }
 
     tracing::debug!("Odesílám Manticore dotaz: {}", manticore_query);
 
@@ -32,11 +272,12 @@ pub async fn search_manticore(
         .post(&search_url)
         .json(&manticore_query)
         .send()
-        .await?;
+        .await
+        .map_err(request_error)?;
 
     // 2. Zjistíme HTTP status a získáme text
     let status = response.status();
-    let response_text = response.text().await?;
+    let response_text = response.text().await.map_err(request_error)?;
 
     // 3. ZALOGUJEME SUROVÝ TEXT ODPOVĚDI (TOHLE JE KLÍČOVÉ)
     tracing::debug!(
@@ -50,10 +291,7 @@ pub async fn search_manticore(
         // Vrátíme chybu s textem, který poslal Manticore
         let err_msg = format!("Manticore vrátil chybu (Status {}): {}", status, response_text);
         // Převedeme na 'reqwest::Error'
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            err_msg,
-        ).into());
+        return Err(std::io::Error::other(err_msg).into());
     }
 
    // 5. Status byl OK, teď se pokusíme text parsovat na náš struct
@@ -71,4 +309,343 @@ pub async fn search_manticore(
         })?; // Tento '?' teď bude spokojený
     Ok(manticore_response)
     // ---- KONEC ZMĚNY ----
-}
\ No newline at end of file
+}
+
+/// Převede 'hit' na kartu výsledku.
+///
+/// Nadpis je zvýrazněné pole 'title_field', případně jeho hodnota
+/// z '_source', nebo aspoň ID. Úryvky ostatních polí jsou v pořadí
+/// podle 'highlight_fields' (jinak abecedně).
+pub fn search_result(mut hit: ManticoreHit, title_field: &str, highlight_fields: &[String]) -> SearchResult {
+    let id = match &hit._id {
+        serde_json::Value::String(id) => id.clone(),
+        other => other.to_string(),
+    };
+
+    let title = match hit.highlight.remove(title_field).and_then(|s| s.into_iter().next()) {
+        Some(snippet) => highlight_parts(&snippet),
+        None => {
+            let text = match hit._source.get(title_field) {
+                Some(serde_json::Value::String(text)) => text.clone(),
+                Some(other) if !other.is_null() => other.to_string(),
+                _ => format!("#{}", id),
+            };
+            vec![HighlightPart { text, mark: false }]
+        }
+    };
+
+    let mut fields: Vec<(String, Vec<String>)> = Vec::new();
+    for field in highlight_fields {
+        if let Some(snippets) = hit.highlight.remove(field) {
+            fields.push((field.clone(), snippets));
+        }
+    }
+    fields.extend(hit.highlight);
+    let snippets = fields
+        .into_iter()
+        .flat_map(|(field, snippets)| {
+            snippets.into_iter().map(move |snippet| SearchSnippet {
+                field: field.clone(),
+                parts: highlight_parts(&snippet),
+            })
+        })
+        .collect();
+
+    SearchResult {
+        id,
+        score: hit._score,
+        title,
+        snippets,
+        source: hit._source,
+    }
+}
+
+/// Rozdělí úryvek podle značek 'MARK_START'/'MARK_END' na kusy textu
+fn highlight_parts(snippet: &str) -> Vec<HighlightPart> {
+    let mut parts = Vec::new();
+    let mut rest = snippet;
+    while let Some(start) = rest.find(MARK_START) {
+        if start > 0 {
+            parts.push(HighlightPart { text: rest[..start].to_string(), mark: false });
+        }
+        let marked = &rest[start + MARK_START.len_utf8()..];
+        // Chybějící koncová značka (useknutý úryvek) = shoda do konce
+        let end = marked.find(MARK_END).unwrap_or(marked.len());
+        parts.push(HighlightPart { text: marked[..end].to_string(), mark: true });
+        rest = marked.get(end + MARK_END.len_utf8()..).unwrap_or("");
+    }
+    if !rest.is_empty() {
+        parts.push(HighlightPart { text: rest.to_string(), mark: false });
+    }
+    parts
+}
+
+/// Typy sloupců, které lze zadat při vytváření tabulky
+pub const COLUMN_TYPES: [&str; 10] = [
+    "text", "string", "integer", "bigint", "float", "bool", "timestamp", "json", "multi", "multi64",
+];
+
+/// Pošle SQL příkaz na '/sql?mode=raw' a vrátí sady výsledků.
+///
+/// Chyba HTTP i chyba v kterékoliv sadě výsledků se vrací jako 'Err'.
+/// Názvy v 'query' musí být už zvalidované ('validate_field').
+async fn sql_raw(
+    client: &ManticoreClient,
+    base_url: &str,
+    query: &str,
+) -> Result<Vec<ManticoreSqlResult>, Box<dyn Error>> {
+    let response = client
+        .post(format!("{}/sql?mode=raw", base_url))
+        .form(&[("query", query)])
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    let status = response.status();
+    let response_text = response.text().await.map_err(request_error)?;
+    if !status.is_success() {
+        let err_msg = format!("Manticore vrátil chybu (Status {}): {}", status, response_text);
+        return Err(std::io::Error::other(err_msg).into());
+    }
+
+    let results: Vec<ManticoreSqlResult> = parse_json_from_string(&response_text)?;
+    if let Some(result) = results.iter().find(|result| !result.error.is_empty()) {
+        return Err(std::io::Error::other(format!("Manticore: {}", result.error)).into());
+    }
+    Ok(results)
+}
+
+/// Textová hodnota buňky (raw režim posílá čísla někdy jako text)
+fn cell_text(row: &serde_json::Map<String, Value>, column: &str) -> Option<String> {
+    match row.get(column)? {
+        Value::String(text) => Some(text.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Vrátí tabulky a jejich typy příkazem 'SHOW TABLES', seřazené podle názvu.
+///
+/// Raw režim SQL endpointu vrací
+/// '[{"columns": [...], "data": [{"Table": "...", "Type": "rt"}]}]'.
+/// Starší verze Manticore sloupec pojmenovávají 'Index'.
+pub async fn show_tables(
+    client: &ManticoreClient,
+    base_url: &str,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let results = sql_raw(client, base_url, "SHOW TABLES").await?;
+    let mut tables: Vec<(String, String)> = results
+        .into_iter()
+        .flat_map(|result| result.data)
+        .filter_map(|row| {
+            let name = cell_text(&row, "Table").or_else(|| cell_text(&row, "Index"))?;
+            Some((name, cell_text(&row, "Type").unwrap_or_default()))
+        })
+        .collect();
+    tables.sort();
+    Ok(tables)
+}
+
+/// Názvy tabulek (indexů) pro výběr na stránce /search
+pub async fn list_tables(
+    client: &ManticoreClient,
+    base_url: &str,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let tables = show_tables(client, base_url).await?;
+    Ok(tables.into_iter().map(|(name, _)| name).collect())
+}
+
+/// 'SHOW TABLE STATUS' jako dvojice (proměnná, hodnota)
+pub async fn table_status(
+    client: &ManticoreClient,
+    base_url: &str,
+    table: &str,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    validate_field(table)?;
+    let results = sql_raw(client, base_url, &format!("SHOW TABLE {} STATUS", table)).await?;
+    Ok(results
+        .into_iter()
+        .flat_map(|result| result.data)
+        .filter_map(|row| Some((cell_text(&row, "Variable_name")?, cell_text(&row, "Value").unwrap_or_default())))
+        .collect())
+}
+
+/// Tabulka s počtem dokumentů a velikostí. Stav, který nejde načíst,
+/// zůstane prázdný - seznam tabulek se kvůli tomu nezahodí.
+pub async fn table_info(
+    client: &ManticoreClient,
+    base_url: &str,
+    name: String,
+    kind: String,
+) -> ManticoreTableInfo {
+    let status = table_status(client, base_url, &name).await.unwrap_or_else(|e| {
+        tracing::debug!("Manticore: Stav tabulky '{}' nelze načíst: {}", name, e);
+        Vec::new()
+    });
+    let number = |variable: &str| {
+        status
+            .iter()
+            .find(|(name, _)| name == variable)
+            .and_then(|(_, value)| value.parse().ok())
+    };
+    ManticoreTableInfo {
+        documents: number("indexed_documents"),
+        ram_bytes: number("ram_bytes"),
+        disk_bytes: number("disk_bytes"),
+        name,
+        kind,
+    }
+}
+
+/// Schéma tabulky ('DESCRIBE')
+pub async fn describe_table(
+    client: &ManticoreClient,
+    base_url: &str,
+    table: &str,
+) -> Result<Vec<ManticoreColumn>, Box<dyn Error>> {
+    validate_field(table)?;
+    let results = sql_raw(client, base_url, &format!("DESCRIBE {}", table)).await?;
+    Ok(results
+        .into_iter()
+        .flat_map(|result| result.data)
+        .filter_map(|row| {
+            Some(ManticoreColumn {
+                field: cell_text(&row, "Field")?,
+                kind: cell_text(&row, "Type").unwrap_or_default(),
+                properties: cell_text(&row, "Properties").unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+/// Rozparsuje schéma z formuláře: jeden sloupec na řádek ve tvaru
+/// 'název typ', prázdné řádky se přeskočí
+pub fn parse_schema(schema: &str) -> Result<Vec<(String, String)>, String> {
+    let mut columns: Vec<(String, String)> = Vec::new();
+    for (number, line) in schema.lines().enumerate() {
+        let mut parts = line.split_whitespace();
+        let Some(name) = parts.next() else { continue };
+        let kind = parts.next().unwrap_or("").to_lowercase();
+        if parts.next().is_some() {
+            return Err(format!("řádek {}: očekáváno 'název typ'", number + 1));
+        }
+        validate_field(name).map_err(|e| format!("řádek {}: {}", number + 1, e))?;
+        // 'id' přidává Manticore sám
+        if name.eq_ignore_ascii_case("id") {
+            return Err(format!("řádek {}: sloupec 'id' má každá tabulka automaticky", number + 1));
+        }
+        if !COLUMN_TYPES.contains(&kind.as_str()) {
+            return Err(format!(
+                "řádek {}: neznámý typ '{}' (povolené: {})",
+                number + 1, kind, COLUMN_TYPES.join(", ")
+            ));
+        }
+        if columns.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(name)) {
+            return Err(format!("řádek {}: sloupec '{}' je uveden dvakrát", number + 1, name));
+        }
+        columns.push((name.to_string(), kind));
+    }
+    if columns.is_empty() {
+        return Err("schéma musí mít aspoň jeden sloupec".to_string());
+    }
+    Ok(columns)
+}
+
+/// Vytvoří RT tabulku ('CREATE TABLE'). Sloupce jsou z 'parse_schema'.
+pub async fn create_table(
+    client: &ManticoreClient,
+    base_url: &str,
+    table: &str,
+    columns: &[(String, String)],
+) -> Result<(), Box<dyn Error>> {
+    validate_field(table)?;
+    let columns = columns
+        .iter()
+        .map(|(name, kind)| format!("{} {}", name, kind))
+        .collect::<Vec<_>>()
+        .join(", ");
+    sql_raw(client, base_url, &format!("CREATE TABLE {}({})", table, columns)).await?;
+    Ok(())
+}
+
+/// Smaže všechny dokumenty RT tabulky ('TRUNCATE TABLE')
+pub async fn truncate_table(
+    client: &ManticoreClient,
+    base_url: &str,
+    table: &str,
+) -> Result<(), Box<dyn Error>> {
+    validate_field(table)?;
+    sql_raw(client, base_url, &format!("TRUNCATE TABLE {}", table)).await?;
+    Ok(())
+}
+
+/// Spustí slučování diskových chunků ('OPTIMIZE TABLE').
+/// Manticore ho provede na pozadí, příkaz se vrátí hned.
+pub async fn optimize_table(
+    client: &ManticoreClient,
+    base_url: &str,
+    table: &str,
+) -> Result<(), Box<dyn Error>> {
+    validate_field(table)?;
+    sql_raw(client, base_url, &format!("OPTIMIZE TABLE {}", table)).await?;
+    Ok(())
+}
+
+/// Chyba HTTP požadavku na Manticore. Vypršený časový limit
+/// ('manticore.timeout_secs') pojmenuje přímo - reqwest sám hlásí
+/// jen obecné "error sending request".
+fn request_error(e: reqwest::Error) -> Box<dyn Error> {
+    if e.is_timeout() {
+        let message = "požadavek nedoběhl v časovém limitu (manticore.timeout_secs)";
+        return std::io::Error::new(std::io::ErrorKind::TimedOut, message).into();
+    }
+    e.into()
+}
+
+/// Zapíše dokumenty do RT indexu přes '/bulk' (NDJSON, jeden 'replace'
+/// na řádek). Existující dokument se stejným ID se přepíše.
+///
+/// Vrací počet zapsaných dokumentů. Když Manticore hlásí chybu
+/// (i jen u části řádků), vrací ji jako 'Err'.
+pub async fn bulk_replace(
+    client: &ManticoreClient,
+    base_url: &str,
+    index: &str,
+    docs: &[(i64, Value)],
+) -> Result<usize, Box<dyn Error>> {
+    if docs.is_empty() {
+        return Ok(0);
+    }
+
+    let mut body = String::new();
+    for (id, doc) in docs {
+        body.push_str(&json!({ "replace": { "index": index, "id": id, "doc": doc } }).to_string());
+        body.push('\n');
+    }
+
+    let response = client
+        .post(format!("{}/bulk", base_url))
+        .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+        .body(body)
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    let status = response.status();
+    let response_text = response.text().await.map_err(request_error)?;
+    if !status.is_success() {
+        let err_msg = format!("Manticore vrátil chybu (Status {}): {}", status, response_text);
+        return Err(std::io::Error::other(err_msg).into());
+    }
+
+    // '{"items": [...], "errors": false, "error": ""}'
+    let result: Value = parse_json_from_string(&response_text)?;
+    if result["errors"].as_bool().unwrap_or(false) {
+        let error = result["error"]
+            .as_str()
+            .filter(|e| !e.is_empty())
+            .map_or_else(|| response_text.clone(), str::to_string);
+        return Err(std::io::Error::other(format!("Manticore odmítl část dávky: {}", error)).into());
+    }
+    Ok(docs.len())
+}
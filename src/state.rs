@@ -1,41 +1,208 @@
 use axum::extract::FromRef;
-use crate::models::MqttStats;
+use chrono::{DateTime, Utc};
+use crate::config::{Config, MqttConfig};
+use crate::models::{BackendStatus, IndexerStats, IngestStats, MqttConnection, MqttStats};
+use crate::services::mqtt_client::{self, LiveMessage, SubscriberStores};
+use crate::services::mqtt_decode::DecoderRegistry;
+use crate::services::mqtt_protocol::MqttClient;
+use crate::services::mqtt_sys::{self, StatsHistory};
+use crate::services::mqtt_topics::TopicTree;
 use redis::Client as ValkeyClient; // valkey db (fork redisu)
 use reqwest::Client as ManticoreClient;
-use std::env;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 use sqlx::{postgres::PgPoolOptions, PgPool}; // database postgresql
 use tera::Tera; // sablony
-use tokio::sync::RwLock;
-
+use tokio::sync::{broadcast, watch, RwLock};
 
+/// První pauza před opakovaným pokusem o připojení
+const RETRY_MIN: Duration = Duration::from_secs(1);
+/// Maximální pauza mezi pokusy (exponenciálně rosteme až sem)
+const RETRY_MAX: Duration = Duration::from_secs(60);
+/// Jak často ověřujeme, že připojený backend pořád žije
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// Jak dlouho čekáme na jedno připojení / ping
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// Obal pro připojení k backendu, které nemusí být dostupné.
+///
+/// Drží buď hotové připojení ('T' - např. 'PgPool'), nebo informaci
+/// o tom, proč připojení není a kdy proběhne další pokus.
+/// O znovupřipojení se stará úloha na pozadí ('spawn_supervisor'),
+/// handlery jen zavolají 'get()' a případně ukážou panel "nedostupné".
 #[derive(Clone)]
-pub struct MqttConfig {
-    pub host: String,
-    pub port: u16,
-    pub refresh_interval_ms: u64, // <-- PŘIDÁNO
+pub struct Backend<T> {
+    name: &'static str,
+    inner: Arc<RwLock<BackendSlot<T>>>,
+}
+
+struct BackendSlot<T> {
+    handle: Option<T>,
+    status: BackendStatus,
+}
+
+impl<T> Backend<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Vytvoří zatím nepřipojený backend
+    pub fn new(name: &'static str) -> Self {
+        Self::disconnected(name, None)
+    }
+
+    /// Vytvoří backend, který se nikdy nepřipojí (chybí konfigurace)
+    pub fn not_configured(name: &'static str, reason: &str) -> Self {
+        Self::disconnected(name, Some(reason.to_string()))
+    }
+
+    fn disconnected(name: &'static str, last_error: Option<String>) -> Self {
+        Self {
+            name,
+            inner: Arc::new(RwLock::new(BackendSlot {
+                handle: None,
+                status: BackendStatus {
+                    connected: false,
+                    since: Utc::now(),
+                    last_error,
+                    attempts: 0,
+                    next_retry_at: None,
+                },
+            })),
+        }
+    }
+
+    /// Vrátí připojení, nebo aktuální stav, pokud backend není dostupný
+    pub async fn get(&self) -> Result<T, BackendStatus> {
+        let slot = self.inner.read().await;
+        match &slot.handle {
+            Some(handle) => Ok(handle.clone()),
+            None => Err(slot.status.clone()),
+        }
+    }
+
+    async fn set_connected(&self, handle: T) {
+        let mut slot = self.inner.write().await;
+        slot.handle = Some(handle);
+        slot.status = BackendStatus {
+            connected: true,
+            since: Utc::now(),
+            last_error: None,
+            attempts: 0,
+            next_retry_at: None,
+        };
+    }
+
+    async fn set_failed(&self, error: String, retry_in: Duration) {
+        let mut slot = self.inner.write().await;
+        let was_connected = slot.handle.take().is_some();
+        slot.status = BackendStatus {
+            connected: false,
+            since: if was_connected { Utc::now() } else { slot.status.since },
+            last_error: Some(error),
+            attempts: slot.status.attempts + 1,
+            next_retry_at: chrono::Duration::from_std(retry_in)
+                .ok()
+                .map(|d| Utc::now() + d),
+        };
+    }
+
+    /// Spustí úlohu na pozadí, která backend připojí a hlídá.
+    ///
+    /// 'connect' vytvoří nové připojení, 'ping' ověří, že stávající
+    /// připojení žije. Při chybě se pokusy opakují s exponenciálním
+    /// odstupem ('RETRY_MIN' .. 'RETRY_MAX').
+    pub fn spawn_supervisor<C, CF, P, PF>(&self, connect: C, ping: P)
+    where
+        C: Fn() -> CF + Send + 'static,
+        CF: Future<Output = Result<T, String>> + Send,
+        P: Fn(T) -> PF + Send + 'static,
+        PF: Future<Output = Result<(), String>> + Send,
+    {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            let mut backoff = RETRY_MIN;
+            loop {
+                let current = backend.inner.read().await.handle.clone();
+                match current {
+                    None => {
+                        let result = tokio::time::timeout(CONNECT_TIMEOUT, connect())
+                            .await
+                            .unwrap_or_else(|_| Err("Timeout při připojování".to_string()));
+                        match result {
+                            Ok(handle) => {
+                                tracing::info!("Úspěšně připojeno k {}.", backend.name);
+                                backend.set_connected(handle).await;
+                                backoff = RETRY_MIN;
+                                tokio::time::sleep(CHECK_INTERVAL).await;
+                            }
+                            Err(e) => {
+                                tracing::warn!(
+                                    "Nepodařilo se připojit k {}: {} (další pokus za {} s)",
+                                    backend.name, e, backoff.as_secs()
+                                );
+                                backend.set_failed(e, backoff).await;
+                                tokio::time::sleep(backoff).await;
+                                backoff = (backoff * 2).min(RETRY_MAX);
+                            }
+                        }
+                    }
+                    Some(handle) => {
+                        let result = tokio::time::timeout(CONNECT_TIMEOUT, ping(handle))
+                            .await
+                            .unwrap_or_else(|_| Err("Timeout při kontrole spojení".to_string()));
+                        match result {
+                            Ok(()) => tokio::time::sleep(CHECK_INTERVAL).await,
+                            Err(e) => {
+                                tracing::warn!("Spojení s {} ztraceno: {}", backend.name, e);
+                                // Hned zkusíme nové připojení
+                                backend.set_failed(e, Duration::ZERO).await;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub tera: Tera,
     pub app_name: String,
-    pub postgres_pool: PgPool,
-    pub valkey_client: ValkeyClient,
+    // Výsledná konfigurace (soubor + env + CLI)
+    pub config: Config,
+    pub postgres_pool: Backend<PgPool>,
+    pub valkey_client: Backend<ValkeyClient>,
     pub mqtt_config: MqttConfig,
-    // PŘIDÁNO: Sdílené, thread-safe úložiště pro MQTT data
-    pub mqtt_stats: Arc<RwLock<MqttStats>>,
+    // PŘIDÁNO: Sdílené, thread-safe úložiště pro MQTT data.
+    // 'watch' kanál - čtení přes 'borrow()', na změny lze čekat ('changed()').
+    pub mqtt_stats: watch::Receiver<MqttStats>,
+    // Čas poslední události z MQTT event loopu (pro health kontrolu)
+    pub mqtt_last_event: Arc<RwLock<Option<DateTime<Utc>>>>,
+    // Stav spojení sběrače (připojeno / znovu se připojuje, poslední chyba)
+    pub mqtt_connection: Arc<RwLock<MqttConnection>>,
+    // Kruhový buffer vzorků statistik (sparklines, /api/v1/mqtt/stats/history)
+    pub mqtt_history: Arc<RwLock<StatsHistory>>,
+    // Strom témat s poslední zprávou (průzkumník /mqtt/topics)
+    pub mqtt_topics: Arc<RwLock<TopicTree>>,
+    // Rozesílání přijatých zpráv do živého výpisu ('subscribe()' pro každý WebSocket)
+    pub mqtt_live: broadcast::Sender<Arc<LiveMessage>>,
+    // Dekodéry payloadů podle témat (průzkumník, živý výpis, ingest)
+    pub mqtt_decoders: Arc<DecoderRegistry>,
+    // Klient sběrače - přes něj publikujeme zprávy z dashboardu
+    pub mqtt_client: MqttClient,
+    // Statistiky mostu MQTT -> Timescale ('None' = vypnutý)
+    pub ingest_stats: Option<Arc<RwLock<IngestStats>>>,
     pub manticore_client: ManticoreClient,
     pub manticore_base_url: String,
+    // Stav indexeru Postgres -> Manticore ('None' = vypnutý)
+    pub indexer_stats: Option<Arc<RwLock<IndexerStats>>>,
 }
 
 #[derive(Default)]
 pub struct AppStateBuilder {
-    app_name: Option<String>,
-    postgres_url: Option<String>,
-    valkey_url: Option<String>,
-    manticore_url: Option<String>,
+    config: Option<Config>,
 }
 
 // ---- ZMĚNA ZDE ----
@@ -53,120 +220,200 @@ impl AppStateBuilder {
         Self::default()
     }
 
-    pub fn app_name(mut self, name: &str) -> Self {
-        self.app_name = Some(name.to_string());
-        self
-    }
-    
-    // Nová metoda pro builder
-    pub fn postgres_url(mut self, url: String) -> Self {
-        self.postgres_url = Some(url);
-        self
-    }
-
-    // Nová metoda pro builder
-    pub fn valkey_url(mut self, url: String) -> Self {
-        self.valkey_url = Some(url);
-        self
-    }
-
-    pub fn manticore_url(mut self, url: String) -> Self {
-        self.manticore_url = Some(url);
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
         self
     }
 
     /// Finální metoda, která sestaví 'AppState'.
     /// PŘIDÁVÁME 'async', protože načítání šablon z disku
     /// může selhat a je to I/O operace.
+    ///
+    /// Nedostupný backend aplikaci neshodí - připojení se
+    /// naváže na pozadí, jakmile backend naběhne.
     pub async fn build(self) -> AppState {
         let tera = Tera::new("templates/**/*.html")
             .expect("Nepodařilo se načíst Tera šablony");
 
-        // ---- PŘIDÁNO: Připojení k Postgres ----
-        let db_url = self.postgres_url
-            .expect("DATABASE_URL musí být nastaven");
-        
-        let pg_pool = PgPoolOptions::new()
-            .max_connections(5) // Nastavíme max 5 připojení v poolu
-            .connect(&db_url)
-            .await // Asynchronně se připojíme
-            .expect("Nepodařilo se připojit k Postgres databázi");
-        
-        tracing::info!("Úspěšně připojeno k Postgres databázi.");
-        // ---- KONEC PŘIDÁNÍ ----
+        let config = self.config.unwrap_or_default();
 
-        // ---- PŘIDÁNO: Připojení k Valkey ----
-        let valkey_url = self.valkey_url
-            .expect("VALKEY_URL musí být nastaven");
-            
-        let valkey_client = ValkeyClient::open(valkey_url)
-            .expect("Nepodařilo se vytvořit Valkey/Redis klienta");
-        
-        // Otestujeme připojení (volitelný, ale dobrý krok)
-         
-        let mut con = valkey_client.get_multiplexed_async_connection().await            
-            .expect("Nepodařilo se získat Valkey/Redis připojení");
-        let _ : () = redis::cmd("PING").query_async(&mut con).await
-            .expect("PING na Valkey/Redis selhal");
-            
-        tracing::info!("Úspěšně připojeno k Valkey/Redis.");
-        // ---- KONEC PŘIDÁNÍ ----
+        // ---- Připojení k Postgres (na pozadí) ----
+        let max_connections = config.postgres.max_connections;
+        let pg_backend = match config.postgres.url.clone() {
+            Some(db_url) => {
+                let backend = Backend::new("Postgres");
+                backend.spawn_supervisor(
+                    move || {
+                        let db_url = db_url.clone();
+                        async move {
+                            PgPoolOptions::new()
+                                .max_connections(max_connections) // Velikost poolu z konfigurace
+                                .acquire_timeout(CONNECT_TIMEOUT)
+                                .connect(&db_url)
+                                .await
+                                .map_err(|e| e.to_string())
+                        }
+                    },
+                    |pool: PgPool| async move {
+                        sqlx::query("SELECT 1")
+                            .execute(&pool)
+                            .await
+                            .map(|_| ())
+                            .map_err(|e| e.to_string())
+                    },
+                );
+                backend
+            }
+            None => {
+                tracing::error!("DATABASE_URL není nastaven, Postgres nebude dostupný.");
+                Backend::not_configured("Postgres", "DATABASE_URL není nastaven")
+            }
+        };
 
-      // --- MQTT Konfigurace ---
-        let mqtt_host = env::var("MQTT_HOST")
-            .expect("Proměnná MQTT_HOST není nastavena");
-        let mqtt_port = env::var("MQTT_PORT")
-            .expect("Proměnná MQTT_PORT není nastavena")
-            .parse::<u16>()
-            .expect("MQTT_PORT musí být číslo");
-        let mqtt_refresh_ms = env::var("MQTT_REFRESH_INTERVAL_MS")
-            .unwrap_or_else(|_| "30000".to_string())
-            .parse::<u64>()
-            .expect("MQTT_REFRESH_INTERVAL_MS musí být číslo");
-
-        let mqtt_config = MqttConfig {
-            host: mqtt_host,
-            port: mqtt_port,
-            refresh_interval_ms: mqtt_refresh_ms,
+        // ---- Připojení k Valkey (na pozadí) ----
+        let valkey_backend = match config.valkey.url.clone() {
+            Some(valkey_url) => {
+                let backend = Backend::new("Valkey");
+                backend.spawn_supervisor(
+                    move || {
+                        let valkey_url = valkey_url.clone();
+                        async move {
+                            let client = ValkeyClient::open(valkey_url)
+                                .map_err(|e| e.to_string())?;
+                            valkey_ping(&client).await?;
+                            Ok(client)
+                        }
+                    },
+                    |client: ValkeyClient| async move { valkey_ping(&client).await },
+                );
+                backend
+            }
+            None => {
+                tracing::error!("VALKEY_URL není nastaven, Valkey nebude dostupný.");
+                Backend::not_configured("Valkey", "VALKEY_URL není nastaven")
+            }
         };
-        tracing::info!("MQTT konfigurace načtena.");
+
+      // --- MQTT Konfigurace ---
+        let mqtt_config = config.mqtt.clone();
 
         // ---- PŘIDÁNO: Vytvoření úložiště a spuštění sběrače ----
-        
+
         // 1. Vytvoříme prázdné úložiště dat
-        let stats_store = Arc::new(RwLock::new(MqttStats::default()));
+        let (stats_tx, stats_rx) = watch::channel(MqttStats::default());
+        let last_event_store = Arc::new(RwLock::new(None));
+        let topic_store = Arc::new(RwLock::new(TopicTree::new(mqtt_config.max_topics)));
+        let connection_store = Arc::new(RwLock::new(mqtt_client::initial_connection()));
+        let (live_tx, _) = broadcast::channel(mqtt_client::LIVE_CHANNEL_CAPACITY);
+        let decoders = Arc::new(DecoderRegistry::from_config(&mqtt_config.decoders));
 
         // 2. Spustíme sběrač na pozadí.
-        //    Předáme mu konfiguraci a "pointer" na úložiště.
-        crate::services::mqtt_client::launch_mqtt_subscriber(
+        //    Předáme mu konfiguraci a "pointery" na úložiště.
+        let mqtt_client = mqtt_client::launch_mqtt_subscriber(
             mqtt_config.clone(), // Sběrač potřebuje konfiguraci
-            stats_store.clone()  // Sběrač potřebuje úložiště
+            SubscriberStores {
+                stats: stats_tx, // Sběrač potřebuje úložiště
+                live: live_tx.clone(), // ...rozesílá zprávy do živého výpisu
+                last_event: last_event_store.clone(), // ...hlásí, že event loop žije
+                topics: topic_store.clone(), // ...plní strom témat
+                connection: connection_store.clone(), // ...a hlásí stav spojení
+            },
+        );
+
+        // 3. Historie statistik - vzorkuje se z téhož úložiště
+        let history_store = Arc::new(RwLock::new(StatsHistory::new(
+            Duration::from_secs(mqtt_config.history_minutes * 60),
+            Duration::from_secs(mqtt_config.history_interval_secs),
+        )));
+        mqtt_sys::launch_history_sampler(
+            stats_rx.clone(),
+            connection_store.clone(),
+            history_store.clone(),
         );
         // ---- KONEC PŘIDÁNÍ ----
 
+        // ---- Most MQTT -> Timescale (jen pokud je zapnutý) ----
+        let ingest_stats = if config.ingest.enabled {
+            let stats = Arc::new(RwLock::new(IngestStats::default()));
+            crate::services::mqtt_ingest::launch_ingest(
+                &mqtt_config,
+                config.ingest.clone(),
+                decoders.clone(),
+                pg_backend.clone(),
+                stats.clone(),
+            );
+            Some(stats)
+        } else {
+            None
+        };
+
         // ---- PŘIDÁNO: Vytvoření Manticore klienta ----
-        
-        // Uložíme si URL do proměnné prostředí.
-        // Je to jednodušší než ji předávat přes AppState,
-        // protože ji 'service' funkce potřebuje jako String.
-        let manticore_url = self.manticore_url
-            .expect("MANTICORE_URL musí být nastaven");
-        
-        
-        // Vytvoříme klienta. Je 'lehký' a sdílený.
-        let manticore_client = ManticoreClient::new();
+
+        // HTTP klient se nepřipojuje předem, každý dotaz
+        // selže (nebo uspěje) sám za sebe.
+        let manticore_url = config.manticore.url.clone().unwrap_or_else(|| {
+            tracing::error!("MANTICORE_URL není nastaven, hledání nebude dostupné.");
+            String::new()
+        });
+
+
+        // Vytvoříme klienta. Je 'lehký' a sdílený. Časový limit platí
+        // pro celý požadavek - zaseknutý '/bulk' tak nezablokuje indexer.
+        let manticore_client = ManticoreClient::builder()
+            .timeout(Duration::from_secs(config.manticore.timeout_secs))
+            .build()
+            .expect("Nepodařilo se vytvořit Manticore HTTP klienta");
         tracing::info!("Manticore HTTP klient vytvořen.");
 
+        // ---- Indexer Postgres -> Manticore (jen pokud je zapnutý) ----
+        let indexer_stats = if config.indexer.enabled {
+            let stats = Arc::new(RwLock::new(IndexerStats::default()));
+            crate::services::manticore_indexer::launch_indexer(
+                config.indexer.clone(),
+                manticore_client.clone(),
+                manticore_url.clone(),
+                pg_backend.clone(),
+                valkey_backend.clone(),
+                stats.clone(),
+            );
+            Some(stats)
+        } else {
+            None
+        };
+
         AppState {
-            tera: tera,
-            app_name: self.app_name.unwrap_or_else(|| "Výchozí App".to_string()),
-            postgres_pool: pg_pool, 
-            valkey_client: valkey_client,
-            mqtt_config: mqtt_config,
-            mqtt_stats: stats_store,
-            manticore_client: manticore_client,
+            tera,
+            app_name: config.app.name.clone(),
+            config,
+            postgres_pool: pg_backend,
+            valkey_client: valkey_backend,
+            mqtt_config,
+            mqtt_stats: stats_rx,
+            mqtt_last_event: last_event_store,
+            mqtt_connection: connection_store,
+            mqtt_history: history_store,
+            mqtt_topics: topic_store,
+            mqtt_live: live_tx,
+            mqtt_decoders: decoders,
+            mqtt_client,
+            ingest_stats,
+            manticore_client,
             manticore_base_url: manticore_url,
+            indexer_stats,
         }
     }
 
-}
\ No newline at end of file
+}
+
+/// Otestuje Valkey připojení příkazem 'PING'
+async fn valkey_ping(client: &ValkeyClient) -> Result<(), String> {
+    let mut con = client
+        .get_multiplexed_async_connection()
+        .await
+        .map_err(|e| e.to_string())?;
+    redis::cmd("PING")
+        .query_async::<_, String>(&mut con)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}